@@ -0,0 +1,373 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, TimeZone, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::credentials::write_private;
+use crate::http::{field, parse, send};
+use crate::redact;
+use crate::retry::RetryPolicy;
+use crate::services::{Endpoint, Platform, Services};
+use crate::PublishTypes;
+
+/// The claims of a Hitman bearer token we care about.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Claims {
+    #[serde(default)]
+    pub userid: Option<String>,
+    #[serde(default)]
+    pub unique_name: Option<String>,
+    #[serde(default)]
+    pub sub: Option<String>,
+    #[serde(default)]
+    pub platform: Option<String>,
+    #[serde(default, rename = "auth:method")]
+    pub auth_method: Option<String>,
+    #[serde(default)]
+    pub iss: Option<String>,
+    /// A string or a list of strings.
+    #[serde(default)]
+    pub aud: Option<Value>,
+    /// Expiry as a unix timestamp.
+    #[serde(default)]
+    pub exp: Option<i64>,
+}
+
+impl Claims {
+    /// Decodes the payload of a JWT without checking its signature.
+    pub fn decode(bearer: &str) -> Result<Self> {
+        let payload = bearer
+            .trim()
+            .trim_start_matches("Bearer ")
+            .split('.')
+            .nth(1)
+            .context("Bearer is not a JWT")?;
+        let payload = base64::decode_config(payload.trim_end_matches('='), base64::URL_SAFE_NO_PAD)
+            .context("Bearer payload is not base64")?;
+        serde_json::from_slice(&payload).context("Bearer payload is not JSON")
+    }
+
+    pub fn user_id(&self) -> Option<&str> {
+        [&self.userid, &self.unique_name, &self.sub]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .find(|id| uuid::Uuid::parse_str(id).is_ok())
+    }
+
+    pub fn platform(&self) -> Option<&str> {
+        self.platform.as_deref().or(self.auth_method.as_deref())
+    }
+
+    /// The store the token was issued for, from the platform claim or else the audience.
+    pub fn known_platform(&self) -> Option<Platform> {
+        self.platform()
+            .and_then(Platform::from_claim)
+            .or_else(|| self.audiences().iter().find_map(|aud| Platform::from_claim(aud)))
+    }
+
+    pub fn audiences(&self) -> Vec<String> {
+        match &self.aud {
+            Some(Value::String(aud)) => vec![aud.clone()],
+            Some(Value::Array(aud)) => aud
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        Utc.timestamp_opt(self.exp?, 0).single()
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at().is_some_and(|exp| exp <= Utc::now())
+    }
+
+    /// The game the token was issued for, guessed from its audience and issuer.
+    pub fn game(&self) -> Option<PublishTypes> {
+        let mut names = self.audiences();
+        names.extend(self.iss.clone());
+        let names = names.join(" ").to_lowercase();
+        let hitman1 = ["pc-service", "hm1", "hitman1"]
+            .iter()
+            .any(|n| names.contains(n));
+        let hitman2 = ["pc2-service", "hm2", "hitman2"]
+            .iter()
+            .any(|n| names.contains(n));
+        let hitman3 = ["hm3-service", "hm3", "hitman3"]
+            .iter()
+            .any(|n| names.contains(n));
+        match (hitman1, hitman2, hitman3) {
+            (true, false, false) => Some(PublishTypes::HITMAN1),
+            (false, true, false) => Some(PublishTypes::HITMAN2),
+            (false, false, true) => Some(PublishTypes::HITMAN3),
+            _ => None,
+        }
+    }
+
+    /// Fails when the token is expired or was issued for another game than `game`.
+    pub fn check_for(&self, game: PublishTypes) -> Result<()> {
+        if let Some(exp) = self.expires_at().filter(|_| self.is_expired()) {
+            bail!("Bearer expired at {}", exp);
+        }
+        match self.game() {
+            Some(token_game) if token_game != game => {
+                bail!("Bearer is for {:?}, not {:?}", token_game, game)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Checks `bearer` before a session is opened with it.
+///
+/// Tokens that aren't JWTs can't be inspected and pass with a warning.
+pub fn preflight(bearer: &str, game: PublishTypes) -> Result<()> {
+    match Claims::decode(bearer) {
+        Ok(claims) => claims.check_for(game),
+        Err(e) => {
+            warn!("Skipping bearer pre-flight check: {:#}", e);
+            Ok(())
+        }
+    }
+}
+
+/// Asks `services` for the profile of `user_id`, which only succeeds with a valid bearer.
+pub async fn check_online(
+    client: &reqwest::Client,
+    services: &Services,
+    user_id: &str,
+    bearer: &str,
+) -> Result<()> {
+    let body = json!({
+        "id": user_id,
+        "extensions": [],
+    });
+    send(
+        Endpoint::GetProfile,
+        None,
+        services
+            .headers
+            .apply(client.post(services.getprofile_url()))
+            .bearer_auth(bearer)
+            .body(body.to_string()),
+        &RetryPolicy::none(),
+    )
+    .await?;
+    Ok(())
+}
+
+/// A bearer token and the user it belongs to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Account {
+    /// Shown in logs and summaries instead of the token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub bearer: String,
+    /// Taken from the token when left out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    /// Used to mint a new bearer when the current one expires.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+}
+
+impl Account {
+    pub fn new(bearer: String) -> Self {
+        Account {
+            name: None,
+            bearer,
+            user_id: None,
+            refresh_token: None,
+        }
+    }
+
+    /// Reads a JSON list of accounts:
+    ///
+    /// ```json
+    /// [{ "name": "main", "bearer": "<token>", "user_id": "<optional user id>" }]
+    /// ```
+    pub fn load_all(path: &str) -> Result<Vec<Self>> {
+        let accounts = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read accounts file {}", path))?;
+        serde_json::from_str(&accounts).with_context(|| format!("Invalid accounts file {}", path))
+    }
+
+    /// Writes `accounts` back to an accounts file, e.g. after their tokens were rotated.
+    pub fn save_all(path: &str, accounts: &[Account]) -> Result<()> {
+        let json = serde_json::to_string_pretty(accounts)?;
+        write_private(Path::new(path), &json)
+            .with_context(|| format!("Failed to write accounts file {}", path))
+    }
+
+    pub fn claims(&self) -> Result<Claims> {
+        Claims::decode(&self.bearer)
+    }
+
+    /// Registers the bearer, refresh token and user ID with [`redact`], so logs mask them.
+    pub fn register_secrets(&self) {
+        redact::register(&self.bearer, redact::BEARER);
+        if let Some(refresh_token) = &self.refresh_token {
+            redact::register(refresh_token, redact::REFRESH_TOKEN);
+        }
+        if let Ok(user_id) = self.user_id() {
+            redact::register(&user_id, redact::USER_ID);
+        }
+    }
+
+    /// The configured user ID, or the one in the token. Fails when neither is known.
+    pub fn user_id(&self) -> Result<String> {
+        if let Some(user_id) = &self.user_id {
+            return Ok(user_id.clone());
+        }
+        let claims = self
+            .claims()
+            .map_err(|e| anyhow!("Can't determine the user ID of {}: {:#}", self.label(), e))?;
+        match claims.user_id() {
+            Some(user_id) => Ok(user_id.to_string()),
+            None => bail!(
+                "Can't determine the user ID of {}: the token has no user ID claim",
+                self.label()
+            ),
+        }
+    }
+
+    pub fn platform(&self) -> Option<String> {
+        self.claims().ok()?.platform().map(str::to_string)
+    }
+
+    pub fn known_platform(&self) -> Option<Platform> {
+        self.claims().ok()?.known_platform()
+    }
+
+    /// Whether the bearer is known to be expired and can be renewed.
+    pub fn needs_refresh(&self) -> bool {
+        self.refresh_token.is_some() && self.claims().is_ok_and(|c| c.is_expired())
+    }
+
+    /// Trades the refresh token for a new bearer at the OAuth token endpoint of `services`.
+    ///
+    /// The refresh token is replaced too when the service rotates it. The request is sent
+    /// only once: a refresh that reached the service may already have used up the token,
+    /// and a retry with it would fail anyway.
+    pub async fn refresh(&mut self, client: &reqwest::Client, services: &Services) -> Result<()> {
+        let refresh_token = self
+            .refresh_token
+            .clone()
+            .with_context(|| format!("{} has no refresh token", self.label()))?;
+        redact::register(&refresh_token, redact::REFRESH_TOKEN);
+        let body = send(
+            Endpoint::OAuthToken,
+            None,
+            services
+                .headers
+                .apply(client.post(services.oauthtoken_url()))
+                .form(&[
+                    ("grant_type", "refresh_token"),
+                    ("refresh_token", refresh_token.as_str()),
+                ]),
+            &RetryPolicy::none(),
+        )
+        .await?;
+        let json = parse(Endpoint::OAuthToken, &body)?;
+        self.bearer = field(Endpoint::OAuthToken, &json, "/access_token", &body)?;
+        redact::register(&self.bearer, redact::BEARER);
+        if let Some(rotated) = json["refresh_token"].as_str() {
+            redact::register(rotated, redact::REFRESH_TOKEN);
+            self.refresh_token = Some(rotated.to_string());
+        }
+        info!("Refreshed the bearer of {}", self.label());
+        Ok(())
+    }
+
+    pub fn label(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| "unnamed account".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER_ID: &str = "fe76faee-ecdc-4dd7-a6d5-c5b84054a87c";
+
+    /// An unsigned JWT carrying `claims`.
+    fn jwt(claims: Value) -> String {
+        let encode = |json: Value| base64::encode_config(json.to_string(), base64::URL_SAFE_NO_PAD);
+        format!(
+            "{}.{}.signature",
+            encode(json!({ "alg": "RS256", "typ": "JWT" })),
+            encode(claims)
+        )
+    }
+
+    #[test]
+    fn decodes_valid_jwt() {
+        let exp = Utc::now().timestamp() + 3600;
+        let bearer = jwt(json!({
+            "userid": USER_ID,
+            "platform": "steam",
+            "iss": "auth.hitman.io",
+            "aud": "hm3-service.hitman.io",
+            "exp": exp,
+        }));
+        let claims = Claims::decode(&format!("Bearer {}", bearer)).unwrap();
+        assert_eq!(claims.user_id(), Some(USER_ID));
+        assert_eq!(claims.known_platform(), Some(Platform::Steam));
+        assert_eq!(claims.game(), Some(PublishTypes::HITMAN3));
+        assert_eq!(claims.expires_at().map(|e| e.timestamp()), Some(exp));
+        assert!(!claims.is_expired());
+        assert!(claims.check_for(PublishTypes::HITMAN3).is_ok());
+        assert!(claims.check_for(PublishTypes::HITMAN2).is_err());
+    }
+
+    #[test]
+    fn rejects_tokens_that_are_not_jwts() {
+        let error = Claims::decode("opaque-token").unwrap_err();
+        assert!(format!("{:#}", error).contains("not a JWT"), "{:#}", error);
+        let error = Claims::decode("header.!!!.signature").unwrap_err();
+        assert!(format!("{:#}", error).contains("not base64"), "{:#}", error);
+        // Not a JWT, so it can't be checked, but it isn't refused either.
+        assert!(preflight("opaque-token", PublishTypes::HITMAN3).is_ok());
+    }
+
+    #[test]
+    fn never_expires_without_exp() {
+        let claims = Claims::decode(&jwt(json!({ "sub": USER_ID }))).unwrap();
+        assert_eq!(claims.expires_at(), None);
+        assert!(!claims.is_expired());
+        assert!(claims.check_for(PublishTypes::HITMAN2).is_ok());
+        assert_eq!(claims.user_id(), Some(USER_ID));
+
+        let expired = Claims::decode(&jwt(json!({ "exp": 1 }))).unwrap();
+        assert!(expired.is_expired());
+        assert!(expired.check_for(PublishTypes::HITMAN3).is_err());
+    }
+
+    #[test]
+    fn reads_audience_as_string_or_array() {
+        let single = Claims::decode(&jwt(json!({ "aud": "pc2-service.hitman.io" }))).unwrap();
+        assert_eq!(single.audiences(), ["pc2-service.hitman.io"]);
+        assert_eq!(single.game(), Some(PublishTypes::HITMAN2));
+
+        let many = Claims::decode(&jwt(json!({ "aud": ["hm3-service.hitman.io", "epic", 3] })))
+            .unwrap();
+        assert_eq!(many.audiences(), ["hm3-service.hitman.io", "epic"]);
+        assert_eq!(many.game(), Some(PublishTypes::HITMAN3));
+        assert_eq!(many.known_platform(), Some(Platform::Epic));
+
+        let none = Claims::decode(&jwt(json!({}))).unwrap();
+        assert!(none.audiences().is_empty());
+        assert_eq!(none.game(), None);
+    }
+}
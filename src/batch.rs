@@ -0,0 +1,348 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+
+use crate::contract::PublishedContract;
+use crate::redact::redact;
+use crate::PublishTypes;
+
+/// The outcome of publishing one file to one game with one account.
+#[derive(Debug)]
+pub struct JobResult {
+    pub file: String,
+    pub game: PublishTypes,
+    /// Which account published, never the bearer itself.
+    pub account: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub result: Result<PublishedContract, String>,
+}
+
+/// One job in a machine-readable report. The field names are stable and become the
+/// JSON keys and CSV columns.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportRow {
+    pub file: String,
+    pub game: String,
+    pub account: String,
+    /// `ok` or `failed`.
+    pub status: &'static str,
+    pub contract_id: Option<String>,
+    pub contract_public_id: Option<String>,
+    /// RFC 3339 in UTC.
+    pub started_at: String,
+    pub finished_at: String,
+    pub error: Option<String>,
+}
+
+impl From<&JobResult> for ReportRow {
+    fn from(job: &JobResult) -> Self {
+        let (published, error) = match &job.result {
+            Ok(published) => (Some(published), None),
+            // Service errors echo response bodies, which may hold tokens or IDs.
+            Err(e) => (None, Some(redact(e))),
+        };
+        ReportRow {
+            file: job.file.clone(),
+            game: format!("{:?}", job.game),
+            account: job.account.clone(),
+            status: if published.is_some() { "ok" } else { "failed" },
+            contract_id: published.map(|p| p.contract_id.clone()),
+            contract_public_id: published.map(|p| p.contract_public_id.clone()),
+            started_at: job.started_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+            finished_at: job.finished_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+            error,
+        }
+    }
+}
+
+/// How the results of a batch are reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// [`summary_table`], for people.
+    Table,
+    /// A JSON array of [`ReportRow`]s.
+    Json,
+    /// [`ReportRow`]s with a header line.
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(anyhow!("Unknown output format: {}", s)),
+        }
+    }
+}
+
+/// Renders `results` in `format`.
+pub fn report(results: &[JobResult], format: OutputFormat) -> Result<String> {
+    let rows = results.iter().map(ReportRow::from).collect::<Vec<_>>();
+    match format {
+        OutputFormat::Table => Ok(summary_table(results)),
+        OutputFormat::Json => Ok(serde_json::to_string_pretty(&rows)? + "\n"),
+        OutputFormat::Csv => {
+            // Written by hand so an empty batch still gets the header.
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(vec![]);
+            writer.write_record(CSV_HEADER)?;
+            for row in &rows {
+                writer.serialize(row)?;
+            }
+            Ok(String::from_utf8(writer.into_inner()?)?)
+        }
+    }
+}
+
+const CSV_HEADER: [&str; 9] = [
+    "file",
+    "game",
+    "account",
+    "status",
+    "contract_id",
+    "contract_public_id",
+    "started_at",
+    "finished_at",
+    "error",
+];
+
+/// How a whole batch went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchOutcome {
+    AllSucceeded,
+    SomeFailed,
+    AllFailed,
+    /// There was nothing to publish, e.g. no file or no game was given.
+    NoJobs,
+}
+
+impl BatchOutcome {
+    pub fn of(results: &[JobResult]) -> Self {
+        let failed = results.iter().filter(|r| r.result.is_err()).count();
+        BatchOutcome::from_counts(results.len(), failed)
+    }
+
+    /// The outcome of `jobs` jobs of which `failed` failed, e.g. the plans of a dry run.
+    pub fn from_counts(jobs: usize, failed: usize) -> Self {
+        if jobs == 0 {
+            BatchOutcome::NoJobs
+        } else if failed == 0 {
+            BatchOutcome::AllSucceeded
+        } else if failed < jobs {
+            BatchOutcome::SomeFailed
+        } else {
+            BatchOutcome::AllFailed
+        }
+    }
+
+    /// 0 when everything succeeded, 2 when some jobs failed, 1 when none succeeded and
+    /// 64, the usage error of sysexits.h, when there was no job at all.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            BatchOutcome::AllSucceeded => 0,
+            BatchOutcome::SomeFailed => 2,
+            BatchOutcome::AllFailed => 1,
+            BatchOutcome::NoJobs => 64,
+        }
+    }
+}
+
+/// Renders `results` as a plain text table, one row per job.
+pub fn summary_table(results: &[JobResult]) -> String {
+    let header = [
+        "File",
+        "Game",
+        "Account",
+        "Status",
+        "Contract ID / Error",
+        "Public ID",
+    ];
+    let rows = results
+        .iter()
+        .map(|r| {
+            let (status, id, public_id) = match &r.result {
+                Ok(published) => (
+                    "OK",
+                    published.contract_id.clone(),
+                    published.contract_public_id.clone(),
+                ),
+                Err(e) => (
+                    "FAILED",
+                    redact(e).replace('\n', " "),
+                    String::new(),
+                ),
+            };
+            [
+                r.file.clone(),
+                format!("{:?}", r.game),
+                r.account.clone(),
+                status.to_string(),
+                id,
+                public_id,
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut table = String::new();
+    let mut push_row = |cells: Vec<&str>| {
+        let line = cells
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join(" | ");
+        table.push_str(line.trim_end());
+        table.push('\n');
+    };
+    push_row(header.to_vec());
+    push_row(
+        widths
+            .iter()
+            .map(|w| "-".repeat(*w))
+            .collect::<Vec<_>>()
+            .iter()
+            .map(String::as_str)
+            .collect(),
+    );
+    for row in &rows {
+        push_row(row.iter().map(String::as_str).collect());
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(result: Result<PublishedContract, String>) -> JobResult {
+        let now = Utc::now();
+        JobResult {
+            file: "contract.json".to_string(),
+            game: PublishTypes::HITMAN3,
+            account: "main".to_string(),
+            started_at: now,
+            finished_at: now,
+            result,
+        }
+    }
+
+    fn published() -> JobResult {
+        job(Ok(PublishedContract {
+            contract_id: "ee454990-0c4b-49e5-9572-a67887325283".to_string(),
+            contract_public_id: "1-23-4567890-12".to_string(),
+        }))
+    }
+
+    fn failed() -> JobResult {
+        job(Err("GetForPlay2 failed with 500 Internal Server Error".to_string()))
+    }
+
+    #[test]
+    fn masks_secrets_in_errors() {
+        crate::redact::register("batch-test-bearer-1234", crate::redact::BEARER);
+        let results = [job(Err(
+            "OAuthToken failed with 400 Bad Request: {\"refresh\":\"batch-test-bearer-1234\"}"
+                .to_string(),
+        ))];
+        for format in [OutputFormat::Table, OutputFormat::Json, OutputFormat::Csv] {
+            let report = report(&results, format).unwrap();
+            assert!(!report.contains("batch-test-bearer-1234"), "{}", report);
+            assert!(report.contains("<bearer>"), "{}", report);
+        }
+    }
+
+    #[test]
+    fn writes_valid_csv_for_any_error() {
+        let error = "CreateFromParams failed with 400 Bad Request: {\"message\": \"a, \"b\"\"}\nsecond line";
+        let results = [published(), job(Err(error.to_string()))];
+        let csv = report(&results, OutputFormat::Csv).unwrap();
+
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        assert_eq!(reader.headers().unwrap(), CSV_HEADER.as_slice());
+        let rows = reader
+            .records()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(&rows[0][3], "ok");
+        assert_eq!(&rows[0][5], "1-23-4567890-12");
+        assert_eq!(&rows[0][8], "");
+        assert_eq!(&rows[1][3], "failed");
+        assert_eq!(&rows[1][4], "");
+        assert_eq!(&rows[1][8], error);
+
+        let empty = report(&[], OutputFormat::Csv).unwrap();
+        assert_eq!(empty.lines().collect::<Vec<_>>(), [CSV_HEADER.join(",")]);
+    }
+
+    #[test]
+    fn writes_documented_json_fields() {
+        let json = report(&[published(), failed()], OutputFormat::Json).unwrap();
+        let rows: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let rows = rows.as_array().unwrap();
+        assert_eq!(rows.len(), 2);
+        for row in rows {
+            let mut keys = row.as_object().unwrap().keys().collect::<Vec<_>>();
+            let mut documented = CSV_HEADER.to_vec();
+            keys.sort();
+            documented.sort();
+            assert_eq!(keys, documented);
+            assert_eq!(row["file"], "contract.json");
+            assert_eq!(row["game"], "HITMAN3");
+            assert_eq!(row["account"], "main");
+            assert!(DateTime::parse_from_rfc3339(row["started_at"].as_str().unwrap()).is_ok());
+        }
+        assert_eq!(rows[0]["status"], "ok");
+        assert_eq!(rows[0]["contract_id"], "ee454990-0c4b-49e5-9572-a67887325283");
+        assert_eq!(rows[0]["error"], serde_json::Value::Null);
+        assert_eq!(rows[1]["status"], "failed");
+        assert_eq!(rows[1]["contract_public_id"], serde_json::Value::Null);
+        assert_eq!(
+            rows[1]["error"],
+            "GetForPlay2 failed with 500 Internal Server Error"
+        );
+    }
+
+    #[test]
+    fn exits_0_when_everything_succeeded() {
+        let outcome = BatchOutcome::of(&[published(), published()]);
+        assert_eq!(outcome, BatchOutcome::AllSucceeded);
+        assert_eq!(outcome.exit_code(), 0);
+    }
+
+    #[test]
+    fn exits_2_when_some_jobs_failed() {
+        let outcome = BatchOutcome::of(&[published(), failed()]);
+        assert_eq!(outcome, BatchOutcome::SomeFailed);
+        assert_eq!(outcome.exit_code(), 2);
+    }
+
+    #[test]
+    fn exits_1_when_every_job_failed() {
+        let outcome = BatchOutcome::of(&[failed(), failed()]);
+        assert_eq!(outcome, BatchOutcome::AllFailed);
+        assert_eq!(outcome.exit_code(), 1);
+    }
+
+    #[test]
+    fn exits_with_usage_error_without_jobs() {
+        let outcome = BatchOutcome::of(&[]);
+        assert_eq!(outcome, BatchOutcome::NoJobs);
+        assert_eq!(outcome.exit_code(), 64);
+    }
+}
@@ -0,0 +1,60 @@
+use hitman_contract_creator::mock::{Endpoint, Failure, MockService};
+
+use clap::Parser;
+use log::info;
+
+// Local stand-in for the Hitman online service
+#[derive(Parser, Debug)]
+#[clap(author = "MicroBlock", version, about, long_about = None)]
+struct Args {
+    /// Port to listen on at 127.0.0.1
+    #[clap(long, short, value_parser, default_value_t = 8080)]
+    port: u16,
+
+    /// Make an endpoint fail, e.g. `getforplay2=401`, `createfromparams=malformedjson` or `saveevents2=versionmismatch`
+    #[clap(long, value_parser = parse_failure)]
+    fail: Vec<(Endpoint, Failure)>,
+
+    /// Only accept these game versions in the `Version` header, e.g. `8.20.0`
+    #[clap(long = "accept-version", value_parser)]
+    accept_versions: Vec<String>,
+}
+
+fn parse_failure(s: &str) -> Result<(Endpoint, Failure), String> {
+    let (endpoint, failure) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected <endpoint>=<failure>, got {}", s))?;
+    Ok((
+        endpoint.parse().map_err(|e: anyhow::Error| e.to_string())?,
+        failure.parse().map_err(|e: anyhow::Error| e.to_string())?,
+    ))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    fern::Dispatch::new()
+        .format(|out, message, record| {
+            out.finish(format_args!(
+                "{}[{}][{}] {}",
+                chrono::Local::now().format("[%Y-%m-%d][%H:%M:%S]"),
+                record.target(),
+                record.level(),
+                message
+            ))
+        })
+        .level(log::LevelFilter::Info)
+        .chain(std::io::stdout())
+        .apply()?;
+
+    let args = Args::parse();
+
+    let mock = MockService::start_on(([127, 0, 0, 1], args.port).into()).await?;
+    for (endpoint, failure) in args.fail {
+        mock.fail(endpoint, failure);
+    }
+    mock.accept_versions(&args.accept_versions.iter().map(String::as_str).collect::<Vec<_>>());
+    info!("Mock Hitman service listening on {}", mock.base_url());
+
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
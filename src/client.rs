@@ -0,0 +1,70 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+
+/// Settings of the HTTP client all requests share.
+///
+/// One client keeps its connections open between requests, so the TLS handshake
+/// isn't repeated for every step of a publish.
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub connect_timeout: Duration,
+    /// Limit of a whole request, from connecting to reading the last byte of the response.
+    pub timeout: Duration,
+    /// `http://`, `https://`, `socks5://` or `socks5h://` proxy for every request.
+    /// Left out, the `HTTP_PROXY`/`HTTPS_PROXY`/`ALL_PROXY` environment variables are used.
+    pub proxy: Option<String>,
+    /// Extra root certificates to trust, PEM or DER, one per file.
+    pub ca_certs: Vec<PathBuf>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        ClientConfig {
+            connect_timeout: Duration::from_secs(10),
+            timeout: Duration::from_secs(60),
+            proxy: None,
+            ca_certs: vec![],
+        }
+    }
+}
+
+impl ClientConfig {
+    pub fn build(&self) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(self.connect_timeout)
+            .timeout(self.timeout);
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(
+                reqwest::Proxy::all(proxy).with_context(|| format!("Invalid proxy {}", proxy))?,
+            );
+        }
+        for path in &self.ca_certs {
+            let cert = fs::read(path)
+                .with_context(|| format!("Failed to read CA certificate {}", path.display()))?;
+            let cert = if cert.starts_with(b"-----BEGIN") {
+                reqwest::Certificate::from_pem(&cert)
+            } else {
+                reqwest::Certificate::from_der(&cert)
+            }
+            .with_context(|| format!("Invalid CA certificate {}", path.display()))?;
+            builder = builder.add_root_certificate(cert);
+        }
+        Ok(builder.build()?)
+    }
+}
+
+static DEFAULT_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+/// A client with the [`ClientConfig::default`] settings, built once and shared by
+/// everything that isn't given a client of its own.
+pub(crate) fn default_client() -> Result<reqwest::Client> {
+    if let Some(client) = DEFAULT_CLIENT.get() {
+        return Ok(client.clone());
+    }
+    let client = ClientConfig::default().build()?;
+    Ok(DEFAULT_CLIENT.get_or_init(|| client).clone())
+}
@@ -1,61 +1,78 @@
+use std::collections::BTreeMap;
 use std::iter::repeat_with;
 
-use log::info;
+use log::{info, warn};
+use serde::Serialize;
 use serde_json::{json, Value};
 
-use anyhow::{Context, Result};
-
-use crate::PublishTypes;
-
-pub struct Services {
-    getforplay2: &'static str,
-    saveevents2: &'static str,
-    contractcreatepage: &'static str,
-    createfromparams: &'static str,
-}
-
-pub static HITMAN2: Services = Services {
-    getforplay2:
-        "https://pc2-service.hitman.io/authentication/api/userchannel/ContractsService/GetForPlay2",
-    saveevents2:
-        "https://pc2-service.hitman.io/authentication/api/userchannel/EventsService/SaveEvents2",
-    contractcreatepage:
-        "https://pc2-service.hitman.io/profiles/page/contractcreation/create",
-    createfromparams:
-         "https://pc2-service.hitman.io/authentication/api/userchannel/ContractsService/CreateFromParams"
-};
-
-pub static HITMAN3: Services = Services {
-    getforplay2:
-        "https://hm3-service.hitman.io/authentication/api/userchannel/ContractsService/GetForPlay2",
-    saveevents2:
-        "https://hm3-service.hitman.io/authentication/api/userchannel/EventsService/SaveEvents2",
-    contractcreatepage:
-        "https://hm3-service.hitman.io/profiles/page/contractcreation/create",
-    createfromparams:
-        "https://hm3-service.hitman.io/authentication/api/userchannel/ContractsService/CreateFromParams"
-};
-
+use anyhow::{bail, Result};
+use tokio::sync::Mutex;
+
+use crate::auth::Account;
+use crate::client::default_client;
+use crate::contract_file::ContractFile;
+pub use crate::event::Event;
+use crate::event::{EventContext, KillConditions};
+use crate::error::ServiceError;
+use crate::http::{field, parse, send};
+use crate::redact;
+use crate::retry::RetryPolicy;
+use crate::services::{Endpoint, Services};
+use crate::validate::{check_contents, check_location};
+use crate::version::is_version_mismatch;
+
+/// A contract read from a contract json file, ready to be published to one game.
 pub struct Contract {
-    contract_json: Value,
-    services: &'static Services,
+    contract_json: ContractFile,
+    services: Services,
+    client: reqwest::Client,
+    retry: RetryPolicy,
+    non_idempotent_retry: RetryPolicy,
     get4play: Get4PlayResponse,
+    /// Replaces the playthrough of [`Contract::events`] when set.
+    events: Option<Vec<Event>>,
     session_id: String,
     r#type:crate::PublishTypes
 }
 
+/// IDs the service assigned to a published contract.
+#[derive(Debug, Clone)]
+pub struct PublishedContract {
+    pub contract_id: String,
+    pub contract_public_id: String,
+}
+
+pub const PLACEHOLDER_CONTRACT_SESSION_ID: &str = "<ContractSessionId>";
+pub const PLACEHOLDER_CONTRACT_ID: &str = "<ContractId>";
+pub const PLACEHOLDER_CONTRACT_PUBLIC_ID: &str = "<ContractPublicId>";
+
+/// A request `publish_contract` would send, as built by [`Contract::dry_run`].
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedRequest {
+    pub method: &'static str,
+    pub url: String,
+    /// Sent besides `Authorization`.
+    pub headers: BTreeMap<&'static str, String>,
+    pub body: Option<Value>,
+}
+
 struct Get4PlayResponse {
     game_changers: Vec<String>,
     contract_session_id: String,
 }
 
-#[derive(Debug)]
-enum Event {
-    ContractStartEvent,
-    IntroCutEndEvent,
-    KillEvent { repository_id: String },
-    AllBodiesHiddenEvent,
-    ExitGateEvent,
+fn is_unauthorized(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<ServiceError>()
+        .and_then(ServiceError::status)
+        == Some(reqwest::StatusCode::UNAUTHORIZED)
+}
+
+/// The endpoint that turned the request down, if `error` came from the service.
+fn failed_at(error: &anyhow::Error) -> Option<Endpoint> {
+    error
+        .downcast_ref::<ServiceError>()
+        .map(ServiceError::endpoint)
 }
 
 fn get_random_session_id() -> String {
@@ -67,418 +84,426 @@ fn get_random_session_id() -> String {
 }
 
 impl Contract {
+    /// Wraps a parsed contract json file for publishing to `hitman_service` through `services`.
+    ///
+    /// Use [`Services::official`] to publish to the official servers.
     pub fn from_contract_json(
         contract: Value,
         hitman_service: crate::PublishTypes,
+        services: Services,
     ) -> Result<Self> {
+        Self::from_contract_file(
+            ContractFile::from_json_value(contract)?,
+            hitman_service,
+            services,
+        )
+    }
+
+    pub fn from_contract_file(
+        contract: ContractFile,
+        hitman_service: crate::PublishTypes,
+        services: Services,
+    ) -> Result<Self> {
+        let services = services.for_game(hitman_service);
+        if services.headers.version.is_none() {
+            bail!(
+                "No game version known for {:?}, set {:?}.headers.version in --services to the `Version` header of the game client or find one with probe-version --try <version>",
+                hitman_service,
+                hitman_service
+            );
+        }
         Ok(Contract {
             r#type:hitman_service,
             contract_json: contract,
-            services: if let PublishTypes::HITMAN2 = hitman_service {
-                &HITMAN2
-            } else {
-                &HITMAN3
-            },
+            services,
+            client: default_client()?,
+            retry: RetryPolicy::default(),
+            non_idempotent_retry: RetryPolicy::none(),
             get4play: Get4PlayResponse {
                 game_changers: Vec::new(),
                 contract_session_id: "".to_string(),
             },
+            events: None,
             session_id: get_random_session_id(),
         })
     }
-    pub async fn publish_contract(&mut self, user_id: &String, bearer: &String) -> Result<()> {
-        self.get4play = self.get_for_play2(&bearer).await?;
+    /// Sends every request through `client`, e.g. one built from a
+    /// [`ClientConfig`](crate::ClientConfig) and shared by all contracts of a batch.
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Plays `events` instead of starting, killing every target and leaving, e.g. to meet
+    /// the complications of the contract.
+    pub fn with_events(mut self, events: Vec<Event>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Sets the retry policy of the idempotent requests, GetForPlay2 and the contract create page.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Sets the retry policy of SaveEvents2 and CreateFromParams.
+    ///
+    /// These aren't idempotent: a retried request whose first attempt did reach the
+    /// service may save the events twice or create the contract twice. Defaults to
+    /// [`RetryPolicy::none`].
+    pub fn with_non_idempotent_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.non_idempotent_retry = retry;
+        self
+    }
+
+    /// Plays the contract through on the service and creates it from the played session.
+    pub async fn publish_contract(
+        &mut self,
+        user_id: &String,
+        bearer: &String,
+    ) -> Result<PublishedContract> {
+        self.check()?;
+        crate::auth::preflight(bearer, self.r#type)?;
+        redact::register(bearer, redact::BEARER);
+        redact::register(user_id, redact::USER_ID);
+        redact::register(&self.session_id, redact::SESSION_ID);
+
+        // Only a version turned down by GetForPlay2 is retried: nothing was saved or created
+        // yet, while a retry after SaveEvents2 could leave a half-made contract behind.
+        let mut fallbacks = self.services.headers.fallback_versions.clone().into_iter();
+        loop {
+            match self.publish_once(user_id, bearer).await {
+                Err(e) if is_version_mismatch(&e) => match fallbacks
+                    .next()
+                    .filter(|_| failed_at(&e) == Some(Endpoint::GetForPlay2))
+                {
+                    Some(version) => {
+                        warn!(
+                            "{:?} rejected version {}, retrying with {}: {:#}",
+                            self.r#type,
+                            self.services.headers.version.as_deref().unwrap_or("none"),
+                            version,
+                            e
+                        );
+                        self.services.headers.version = Some(version);
+                    }
+                    None => {
+                        return Err(e.context(
+                            "The service rejected the game version, run probe-version or set headers.version in --services",
+                        ))
+                    }
+                },
+                result => return result,
+            }
+        }
+    }
+
+    /// The version sent in the `Version` header, which may have moved on to one of
+    /// `headers.fallback_versions` of the services.
+    pub fn version(&self) -> Option<&str> {
+        self.services.headers.version.as_deref()
+    }
+
+    async fn publish_once(&mut self, user_id: &String, bearer: &String) -> Result<PublishedContract> {
+        self.get4play = self.get_for_play2(bearer).await?;
 
         info!(
             "Contract Session ID: {}",
             &self.get4play.contract_session_id
         );
 
-        let mut events = vec![Event::ContractStartEvent, Event::IntroCutEndEvent];
+        self.save_events(self.events(), user_id, bearer).await?;
 
-        for kill in self.contract_json["CreateFromParamsJ"]["creationData"]["Targets"]
-            .as_array()
-            .context("Invalid JSON")?
-        {
-            events.push(Event::KillEvent {
-                repository_id: kill["RepositoryId"]
-                    .as_str()
-                    .context("Invalid JSON")?
-                    .to_string(),
-            });
+        self.create(bearer).await
+    }
+
+    /// Publishes the contract with `account`, renewing its bearer when it has a refresh token.
+    ///
+    /// An expired bearer is refreshed up front. When the service rejects the bearer with 401
+    /// anyway, it is refreshed and the publish is tried once more.
+    pub async fn publish_contract_as(&mut self, account: &mut Account) -> Result<PublishedContract> {
+        let shared = Mutex::new(account.clone());
+        let result = self.publish_contract_shared(&shared).await;
+        *account = shared.into_inner();
+        result
+    }
+
+    /// Like [`Contract::publish_contract_as`], with an account that jobs running at the same
+    /// time share.
+    ///
+    /// The account stays locked while its bearer is refreshed, and a job that finds it already
+    /// refreshed by another one takes the new bearer, so a rotating refresh token is used once.
+    pub async fn publish_contract_shared(
+        &mut self,
+        account: &Mutex<Account>,
+    ) -> Result<PublishedContract> {
+        let current = {
+            let mut account = account.lock().await;
+            if account.needs_refresh() {
+                account.refresh(&self.client, &self.services).await?;
+            }
+            account.clone()
+        };
+        let user_id = current.user_id()?;
+        match self.publish_contract(&user_id, &current.bearer).await {
+            Err(e) if current.refresh_token.is_some() && is_unauthorized(&e) => {
+                warn!("{} was rejected, refreshing it: {:#}", current.label(), e);
+                let refreshed = {
+                    let mut account = account.lock().await;
+                    if account.bearer == current.bearer {
+                        account.refresh(&self.client, &self.services).await?;
+                    }
+                    account.clone()
+                };
+                self.publish_contract(&user_id, &refreshed.bearer).await
+            }
+            result => result,
         }
+    }
 
-        events.push(Event::ExitGateEvent);
+    /// Builds every request `publish_contract` would send, without sending any of them.
+    ///
+    /// IDs the service would hand out are replaced with [`PLACEHOLDER_CONTRACT_SESSION_ID`],
+    /// [`PLACEHOLDER_CONTRACT_ID`] and [`PLACEHOLDER_CONTRACT_PUBLIC_ID`].
+    pub fn dry_run(&mut self, user_id: &String) -> Result<Vec<PlannedRequest>> {
+        self.check()?;
 
-        self.save_events(events, user_id, bearer, &self.get4play.contract_session_id)
-            .await?;
+        self.get4play = Get4PlayResponse {
+            game_changers: Vec::new(),
+            contract_session_id: PLACEHOLDER_CONTRACT_SESSION_ID.to_string(),
+        };
 
-        self.create(user_id, bearer).await?;
+        let headers = self
+            .services
+            .headers
+            .headers()
+            .into_iter()
+            .map(|(name, value)| (name, value.to_string()))
+            .collect::<BTreeMap<_, _>>();
+        Ok(vec![
+            PlannedRequest {
+                method: "POST",
+                url: self.services.getforplay2_url(),
+                headers: headers.clone(),
+                body: Some(self.get_for_play2_json()),
+            },
+            PlannedRequest {
+                method: "POST",
+                url: self.services.saveevents2_url(),
+                headers: headers.clone(),
+                body: Some(self.save_events_json(&self.events(), user_id)?),
+            },
+            PlannedRequest {
+                method: "GET",
+                url: self.services.contractcreatepage_url(),
+                headers: headers.clone(),
+                body: None,
+            },
+            PlannedRequest {
+                method: "POST",
+                url: self.services.createfromparams_url(),
+                headers: headers.clone(),
+                body: Some(self.create_from_params_json(
+                    PLACEHOLDER_CONTRACT_ID,
+                    PLACEHOLDER_CONTRACT_PUBLIC_ID,
+                )?),
+            },
+        ])
+    }
 
+    /// The events of a playthrough that kills every target and leaves.
+    /// Refuses contract files the service would turn down. An unknown location is only
+    /// warned about, so contracts can be published on private servers with locations of
+    /// their own.
+    fn check(&self) -> Result<()> {
+        if let Some(problem) = check_location(&self.contract_json, self.r#type) {
+            warn!("{}", problem);
+        }
+        let problems = check_contents(&self.contract_json, self.r#type);
+        if !problems.is_empty() {
+            bail!("Invalid contract json:\n{}", problems.join("\n"));
+        }
         Ok(())
     }
 
-    async fn create(&self, user_id: &String, bearer: &String) -> Result<()> {
-        let result = reqwest::Client::new()
-            .get(self.services.contractcreatepage)
-            .bearer_auth(bearer)
-            .header("Version", if let PublishTypes::HITMAN2=self.r#type{"7.17.0"}else{"8.7.0"})
-            .send()
-            .await?
-            .text()
-            .await?;
+    fn events(&self) -> Vec<Event> {
+        if let Some(events) = &self.events {
+            return events.clone();
+        }
+        let mut events = vec![Event::ContractStartEvent, Event::IntroCutEndEvent];
+
+        // The disguise 47 wears, `None` while still in the suit.
+        let mut worn: Option<String> = None;
+        for kill in &self.contract_json.create_from_params.creation_data.targets {
+            let mut conditions = KillConditions::of(kill);
+            // Change into the required disguise first, so the kill isn't made in the suit.
+            match &conditions.outfit_repository_id {
+                Some(outfit) if worn.as_ref() != Some(outfit) => {
+                    events.push(Event::DisguiseEvent {
+                        repository_id: outfit.clone(),
+                    });
+                    worn = Some(outfit.clone());
+                }
+                Some(_) => {}
+                // Any outfit will do, so the kill is made in whatever is worn.
+                None => conditions.outfit_repository_id = worn.clone(),
+            }
+            events.push(Event::KillEvent {
+                repository_id: kill.repository_id.clone(),
+                conditions,
+            });
+        }
+
+        events.push(Event::ExitGateEvent);
+        events
+    }
+
+    async fn create(&self, bearer: &str) -> Result<PublishedContract> {
+        let result = send(
+            Endpoint::ContractCreatePage,
+            Some(self.r#type),
+            self.get(self.services.contractcreatepage_url())
+                .bearer_auth(bearer),
+            &self.retry,
+        )
+        .await?;
         info!("Contract Create Page Result:{}",result);
-        let result: Value = serde_json::from_str(result.as_str())?;
-        let contractid = result["data"]["Contract"]["ContractId"]
-            .as_str()
-            .context("Invalid Contract Page JSON")?
-            .to_string();
-        let contractpublicid = result["data"]["Contract"]["ContractPublicId"]
-            .as_str()
-            .context("Invalid Contract Page JSON")?
-            .to_string();
+        let json = parse(Endpoint::ContractCreatePage, &result)?;
+        let contractid = field(Endpoint::ContractCreatePage, &json, "/data/Contract/ContractId", &result)?;
+        let contractpublicid = field(
+            Endpoint::ContractCreatePage,
+            &json,
+            "/data/Contract/ContractPublicId",
+            &result,
+        )?;
         info!("Contract ID:{}, Public ID:{}", contractid, contractpublicid);
 
-        let mut json = self.contract_json["CreateFromParamsJ"].clone();
-        json["creationData"]["ContractId"] = serde_json::Value::String(contractid);
-        json["creationData"]["ContractPublicId"] = serde_json::Value::String(contractpublicid);
-        
-        let result = reqwest::Client::new()
-            .post(self.services.createfromparams)
-            .bearer_auth(bearer)
-            .header("Content-Type", "application/json; charset=utf-8")
-            .body(json.to_string())
-            .send()
-            .await?
-            .text()
-            .await?;
+        let json = self.create_from_params_json(&contractid, &contractpublicid)?;
+
+        let result = send(
+            Endpoint::CreateFromParams,
+            Some(self.r#type),
+            self.post(self.services.createfromparams_url())
+                .bearer_auth(bearer)
+                .body(json.to_string()),
+            &self.non_idempotent_retry,
+        )
+        .await?;
+        info!("Create From Params Result:{}", result);
+        parse(Endpoint::CreateFromParams, &result)?;
         info!("Finished.");
-        Ok(())
+        Ok(PublishedContract {
+            contract_id: contractid,
+            contract_public_id: contractpublicid,
+        })
     }
-    async fn save_event2(
-        &self,
-        event: Event,
-        user_id: &String,
-        bearer: &String,
-        contract_session_id: &String,
-    ) -> Result<()> {
-        let json_event = match event {
-            Event::ContractStartEvent => {
-                json!({
-                    "Name": "ContractStart",
-                    "ContractSessionId": self.get4play.contract_session_id,
-                    "ContractId": self.get_contract_id()?,
-                    "Value": {
-                        "Loadout": [],
-                        "Disguise": "00000000-0000-0000-0000-000000000000",
-                        "LocationId": self.get_location_id()?,
-                        "GameChangers": self.get4play.game_changers,
-                        "ContractType": "creation",
-                        "DifficultyLevel": 2.000000 as f32,
-                        "IsHitmanSuit": true,
-                        "SelectedCharacterId": "00000000-0000-0000-0000-000000000000"
-                    },
-                    "XboxGameMode": 2.000000 as f32,
-                    "XboxDifficulty": 0.000000 as f32,
-                    "Timestamp": 0.000000 as f32,
-                    "UserId": user_id,
-                    "SessionId": self.session_id,
-                    "Origin": "gameclient",
-                    "Id": uuid::Uuid::new_v4().to_string()
-                })
-            }
-            Event::IntroCutEndEvent => {
-                json!(
-                        {
-                            "Timestamp": 1.0 as f32,
-                            "Name": "IntroCutEnd",
-                            "ContractSessionId": self.get4play.contract_session_id,
-                            "ContractId": self.get_contract_id()?,
-                            "Value": "",
-                            "UserId": user_id,
-                            "SessionId": self.session_id,
-                            "Origin": "gameclient",
-                            "Id": uuid::Uuid::new_v4().to_string()
-                        }
-                )
-            }
-            Event::KillEvent { ref repository_id } => {
-                json!(
-                        {
-                            "Timestamp": 1.0 as f32,
-                            "Name": "Kill",
-                            "ContractSessionId": self.get4play.contract_session_id,
-                            "ContractId": self.get_contract_id()?,
-                            "Value": {"RepositoryId":repository_id,
-                            "ActorId":0.0 as f32,
-                            "ActorType":1.0 as f32,
-                            "KillType":1.0 as f32,
-                            "KillContext":1.0 as f32,
-                            "BodyPartId":1.0 as f32,
-                            "RoomId":1.0 as f32,
-                            "ExplosionType":1.0 as f32,
-                            "TotalDamage":1000.0 as f32,
-                            "Accident":true,
-                            "Explosive":true,
-                            "Projectile":true,
-                            "Sniper":true,
-                            "IsHeadshot":true,
-                            "IsTarget":true,
-                            "ThroughWall":true,
-                            "IsMoving":true,
-                            "OutfitIsHitmanSuit":true,
-                            "WeaponSilenced":true,
-                            "KillItemRepositoryId":"",
-                            "OutfitRepositoryId":"",
-                            "ActorName":"",
-                            "KillClass":"",
-                            "ActorPosition": "0.0, 0.0, 0.0",
-                            "HeroPosition": "0.0, 0.0, 0.0",
-                            "DamageEvents":[],
-                            "PlayerId":0.0 as f32,
-                            "KillItemInstanceId":"",
-                            "KillItemCategory":"",
-                            "KillMethodBroad":"",
-                            "KillMethodStrict":"",
-                            "History":[]},
-                            "UserId": user_id,
-                            "SessionId": self.session_id,
-                            "Origin": "gameclient",
-                            "Id": uuid::Uuid::new_v4().to_string(),
-
-                        }
-                )
-            }
-            Event::AllBodiesHiddenEvent => todo!(),
-            Event::ExitGateEvent => {
-                json!(
-                        {
-                            "Timestamp": 2.0 as f32,
-                            "Name": "exit_gate",
-                            "ContractSessionId": self.get4play.contract_session_id,
-                            "ContractId": self.get_contract_id()?,
-                            "Value": {
-                                "name_ExitGate":"Exit_Gate",
-                                "RepositoryId":self.get_exit_id()?
-                            },
-                            "UserId": user_id,
-                            "SessionId": self.session_id,
-                            "Origin": "gameclient",
-                            "Id": uuid::Uuid::new_v4().to_string()
-                        }
-                )
-            }
-        };
-
-        let json = json!({
-            "userId":user_id,
-            "values":[json_event]
-        });
-
-        info!("Save Events 2 Emitted. Event:{:#?}", event);
-        let result = reqwest::Client::new()
-            .post(self.services.saveevents2)
-            .bearer_auth(bearer)
-            .body(json.to_string())
-            .send()
-            .await?
-            .text()
-            .await?;
 
-        info!("Save Events 2 Responsed. Response:${:#?}", result);
+    fn get(&self, url: String) -> reqwest::RequestBuilder {
+        self.services.headers.apply(self.client.get(url))
+    }
 
-        Ok(())
+    fn post(&self, url: String) -> reqwest::RequestBuilder {
+        self.services.headers.apply(self.client.post(url))
     }
 
+    fn create_from_params_json(&self, contract_id: &str, contract_public_id: &str) -> Result<Value> {
+        let mut json = self.contract_json.create_from_params.clone();
+        json.creation_data.contract_id = Some(Some(contract_id.to_string()));
+        json.creation_data.contract_public_id = Some(Some(contract_public_id.to_string()));
+        if self.r#type == crate::PublishTypes::HITMAN1 {
+            // The HITMAN (2016) client sends no complication fields at all, not even empty ones.
+            json.creation_data.contract_condition_ids = None;
+            json.creation_data.contract_conditions = None;
+        }
+        Ok(serde_json::to_value(json)?)
+    }
     async fn save_events(
         &self,
         events: Vec<Event>,
         user_id: &String,
         bearer: &String,
-        contract_session_id: &String,
     ) -> Result<()> {
-        let mut events_json: Vec<Value> = Vec::new();
-        for event in &events {
-            let json_event = match event {
-                Event::ContractStartEvent => {
-                    json!({
-                        "Name": "ContractStart",
-                        "ContractSessionId": self.get4play.contract_session_id,
-                        "ContractId": self.get_contract_id()?,
-                        "Value": {
-                            "Loadout": [],
-                            "Disguise": "00000000-0000-0000-0000-000000000000",
-                            "LocationId": self.get_location_id()?,
-                            "GameChangers": self.get4play.game_changers,
-                            "ContractType": "creation",
-                            "DifficultyLevel": 2.000000 as f32,
-                            "IsHitmanSuit": true,
-                            "SelectedCharacterId": "00000000-0000-0000-0000-000000000000"
-                        },
-                        "XboxGameMode": 2.000000 as f32,
-                        "XboxDifficulty": 0.000000 as f32,
-                        "Timestamp": 0.000000 as f32,
-                        "UserId": user_id,
-                        "SessionId": self.session_id,
-                        "Origin": "gameclient",
-                        "Id": uuid::Uuid::new_v4().to_string()
-                    })
-                }
-                Event::IntroCutEndEvent => {
-                    json!(
-                            {
-                                "Timestamp": 1.0 as f32,
-                                "Name": "IntroCutEnd",
-                                "ContractSessionId": self.get4play.contract_session_id,
-                                "ContractId": self.get_contract_id()?,
-                                "Value": "",
-                                "UserId": user_id,
-                                "SessionId": self.session_id,
-                                "Origin": "gameclient",
-                                "Id": uuid::Uuid::new_v4().to_string()
-                            }
-                    )
-                }
-                Event::KillEvent { ref repository_id } => {
-                    json!(
-                            {
-                                "Timestamp": 1.0 as f32,
-                                "Name": "Kill",
-                                "ContractSessionId": self.get4play.contract_session_id,
-                                "ContractId": self.get_contract_id()?,
-                                "Value": {"RepositoryId":repository_id,
-                                "ActorId":0.0 as f32,
-                                "ActorType":1.0 as f32,
-                                "KillType":1.0 as f32,
-                                "KillContext":1.0 as f32,
-                                "BodyPartId":1.0 as f32,
-                                "RoomId":1.0 as f32,
-                                "ExplosionType":1.0 as f32,
-                                "TotalDamage":1000.0 as f32,
-                                "Accident":true,
-                                "Explosive":true,
-                                "Projectile":true,
-                                "Sniper":true,
-                                "IsHeadshot":true,
-                                "IsTarget":true,
-                                "ThroughWall":true,
-                                "IsMoving":true,
-                                "OutfitIsHitmanSuit":true,
-                                "WeaponSilenced":true,
-                                "KillItemRepositoryId":"",
-                                "OutfitRepositoryId":"",
-                                "ActorName":"",
-                                "KillClass":"",
-                                "ActorPosition": "0.0, 0.0, 0.0",
-                                "HeroPosition": "0.0, 0.0, 0.0",
-                                "DamageEvents":[],
-                                "PlayerId":0.0 as f32,
-                                "KillItemInstanceId":"",
-                                "KillItemCategory":"",
-                                "KillMethodBroad":"",
-                                "KillMethodStrict":"",
-                                "History":[]},
-                                "UserId": user_id,
-                                "SessionId": self.session_id,
-                                "Origin": "gameclient",
-                                "Id": uuid::Uuid::new_v4().to_string(),
-
-                            }
-                    )
-                }
-                Event::AllBodiesHiddenEvent => todo!(),
-                Event::ExitGateEvent => {
-                    json!(
-                            {
-                                "Timestamp": 2.0 as f32,
-                                "Name": "exit_gate",
-                                "ContractSessionId": self.get4play.contract_session_id,
-                                "ContractId": self.get_contract_id()?,
-                                "Value": {
-                                    "name_ExitGate":"Exit_Gate",
-                                    "RepositoryId":self.get_exit_id()?
-                                },
-                                "UserId": user_id,
-                                "SessionId": self.session_id,
-                                "Origin": "gameclient",
-                                "Id": uuid::Uuid::new_v4().to_string()
-                            }
-                    )
-                }
-            };
-            events_json.push(json_event);
-        }
-
-        let json = json!({
-            "userId":user_id,
-            "values":events_json
-        });
+        let json = self.save_events_json(&events, user_id)?;
 
         info!("Save Events 2 Emitted. Event:{:#?}", events);
-        let result = reqwest::Client::new()
-            .post(self.services.saveevents2)
-            .bearer_auth(bearer)
-            .body(json.to_string())
-            .send()
-            .await?
-            .text()
-            .await?;
+        let result = send(
+            Endpoint::SaveEvents2,
+            Some(self.r#type),
+            self.post(self.services.saveevents2_url())
+                .bearer_auth(bearer)
+                .body(json.to_string()),
+            &self.non_idempotent_retry,
+        )
+        .await?;
 
         info!("Save Events 2 Responsed. Response:${:#?}", result);
 
         Ok(())
     }
 
-    async fn get_for_play2(&self, bearer: &String) -> Result<Get4PlayResponse> {
-        let get4play2 = json!({
-            "id":self.get_contract_id()?,
+    fn save_events_json(&self, events: &[Event], user_id: &String) -> Result<Value> {
+        let context = EventContext {
+            game: self.r#type,
+            contract_session_id: &self.get4play.contract_session_id,
+            contract_id: &self.contract_json.mission_id,
+            location_id: &self.contract_json.mission_name,
+            exit_id: &self.contract_json.exit_id,
+            game_changers: &self.get4play.game_changers,
+            user_id,
+            session_id: &self.session_id,
+            targets: &self.contract_json.create_from_params.creation_data.targets,
+        };
+        let events_json = events
+            .iter()
+            .map(|event| serde_json::to_value(event.envelope(&context)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(json!({
+            "userId":user_id,
+            "values":events_json
+        }))
+    }
+
+    fn get_for_play2_json(&self) -> Value {
+        json!({
+            "id":self.contract_json.mission_id,
             "locationId":"",
             "extraGameChangerIds":[],
-            "difficultyLevel":2 as i32
-        });
+            "difficultyLevel":2
+        })
+    }
+
+    async fn get_for_play2(&self, bearer: &String) -> Result<Get4PlayResponse> {
+        let get4play2 = self.get_for_play2_json();
 
         info!("Get4Play2 Emitted. JSON:{:#?}", get4play2);
 
-        let result = reqwest::Client::new()
-            .post(self.services.getforplay2)
-            .bearer_auth(bearer)
-            .body(get4play2.to_string())
-            .send()
-            .await?
-            .text()
-            .await?;
+        let result = send(
+            Endpoint::GetForPlay2,
+            Some(self.r#type),
+            self.post(self.services.getforplay2_url())
+                .bearer_auth(bearer)
+                .body(get4play2.to_string()),
+            &self.retry,
+        )
+        .await?;
+
+        let json = parse(Endpoint::GetForPlay2, &result)?;
+        if let Some(contract_session_id) = json["ContractSessionId"].as_str() {
+            redact::register(contract_session_id, redact::CONTRACT_SESSION_ID);
+        }
 
         info!("Get4Play2 Response JSON:{:#?}", result);
 
-        let result: Value = serde_json::from_str(result.as_str())?;
-
         Ok(Get4PlayResponse {
-            contract_session_id: String::from(
-                result["ContractSessionId"]
-                    .as_str()
-                    .context("Wrong JSON:Contract Session ID not found.")?,
-            ),
-            game_changers: serde_json::from_value(
-                result["Contract"]["Data"]["GameChangers"].clone(),
-            )?,
+            contract_session_id: field(Endpoint::GetForPlay2, &json, "/ContractSessionId", &result)?,
+            game_changers: serde_json::from_value(json["Contract"]["Data"]["GameChangers"].clone())
+                .map_err(|e| ServiceError::InvalidResponse {
+                    endpoint: Endpoint::GetForPlay2,
+                    reason: format!("Contract.Data.GameChangers: {}", e),
+                    body: result.clone(),
+                })?,
         })
     }
-    fn get_contract_id(&self) -> Result<String> {
-        Ok(self.contract_json["MissionId"]
-            .as_str()
-            .context("Invalid contract json: No Mission Id Found")?
-            .to_string())
-    }
-    fn get_exit_id(&self) -> Result<String> {
-        Ok(self.contract_json["ExitId"]
-            .as_str()
-            .context("Invalid contract json: No Mission Id Found")?
-            .to_string())
-    }
-    fn get_location_id(&self) -> Result<String> {
-        Ok(self.contract_json["MissionName"]
-            .as_str()
-            .context("Invalid contract json: No MissionName Found")?
-            .to_string())
-    }
 }
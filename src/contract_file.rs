@@ -0,0 +1,246 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+
+use anyhow::{anyhow, Result};
+
+/// A contract json file as written by the user.
+///
+/// Every struct keeps the fields it doesn't know about in `extra`, so a file
+/// survives a parse/serialize round trip unchanged. For the same reason optional
+/// fields are `None` when left out and `Some(None)` when explicitly `null`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ContractFile {
+    pub mission_id: String,
+    pub exit_id: String,
+    /// The location id of the mission, e.g. `LOCATION_PARENT_MIAMI`.
+    pub mission_name: String,
+    #[serde(rename = "CreateFromParamsJ")]
+    pub create_from_params: CreateFromParams,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// The body sent to `CreateFromParams`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateFromParams {
+    #[serde(rename = "creationData")]
+    pub creation_data: CreationData,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CreationData {
+    #[serde(default, deserialize_with = "keep_null")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<Option<String>>,
+    /// The briefing.
+    #[serde(default, deserialize_with = "keep_null")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<Option<String>>,
+    #[serde(default, deserialize_with = "keep_null")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "keep_null")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contract_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "keep_null")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contract_public_id: Option<Option<String>>,
+    pub targets: Vec<Target>,
+    #[serde(default, deserialize_with = "keep_null")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub objectives: Option<Option<Vec<Objective>>>,
+    #[serde(default, deserialize_with = "keep_null")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contract_condition_ids: Option<Option<Vec<String>>>,
+    #[serde(default, deserialize_with = "keep_null")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contract_conditions: Option<Option<Vec<ContractCondition>>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Target {
+    pub repository_id: String,
+    #[serde(default, deserialize_with = "keep_null")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selected: Option<Option<bool>>,
+    #[serde(default, deserialize_with = "keep_null")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weapon: Option<Option<TargetWeapon>>,
+    #[serde(default, deserialize_with = "keep_null")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outfit: Option<Option<TargetOutfit>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// The kill method a target has to be eliminated with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TargetWeapon {
+    #[serde(default, deserialize_with = "keep_null")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repository_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "keep_null")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kill_method_broad: Option<Option<String>>,
+    #[serde(default, deserialize_with = "keep_null")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kill_method_strict: Option<Option<String>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// The disguise a target has to be eliminated in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TargetOutfit {
+    #[serde(default, deserialize_with = "keep_null")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repository_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "keep_null")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required: Option<Option<bool>>,
+    #[serde(default, deserialize_with = "keep_null")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_hitman_suit: Option<Option<bool>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Objective {
+    #[serde(default, deserialize_with = "keep_null")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "keep_null")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<Option<String>>,
+    #[serde(default, deserialize_with = "keep_null")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub objective_type: Option<Option<String>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ContractCondition {
+    #[serde(default, deserialize_with = "keep_null")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "keep_null")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<Option<String>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Tells an explicit `null` (`Some(None)`) apart from a missing field, which `default` makes `None`.
+fn keep_null<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::deserialize(deserializer).map(Some)
+}
+
+impl ContractFile {
+    /// Parses a contract file, reporting the path of the offending field on failure.
+    pub fn from_json_str(json: &str) -> Result<Self> {
+        let de = &mut serde_json::Deserializer::from_str(json);
+        serde_path_to_error::deserialize(de)
+            .map_err(|e| anyhow!("Invalid contract json at `{}`: {}", e.path(), e.inner()))
+    }
+
+    pub fn from_json_value(json: Value) -> Result<Self> {
+        serde_path_to_error::deserialize(json)
+            .map_err(|e| anyhow!("Invalid contract json at `{}`: {}", e.path(), e.inner()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn contract_json() -> Value {
+        json!({
+            "MissionId": "b2aac100-dfc7-4f85-b9cd-528114436f6c",
+            "ExitId": "2f8bd7e4-9154-4c8d-8e1e-9a5b4c5d9a3b",
+            "MissionName": "LOCATION_PARENT_MIAMI",
+            "Unknown": { "nested": [1, 2] },
+            "CreateFromParamsJ": {
+                "Unknown": "create",
+                "creationData": {
+                    "Title": "Test contract",
+                    "Description": null,
+                    "Unknown": true,
+                    "Targets": [{
+                        "RepositoryId": "ee454990-0c4b-49e5-9572-a67887325283",
+                        "Selected": true,
+                        "Unknown": 1,
+                        "Weapon": {
+                            "RepositoryId": null,
+                            "KillMethodBroad": "pistol",
+                            "RequiredKillMethodType": 3
+                        },
+                        "Outfit": {
+                            "RepositoryId": "fd56a934-f402-4b02-bdc0-e4d1e5b4f1e3",
+                            "Required": true,
+                            "IsHitmanSuit": null,
+                            "Unknown": "outfit"
+                        }
+                    }],
+                    "Objectives": [{ "Id": "objective", "Unknown": [] }],
+                    "ContractConditionIds": null,
+                    "ContractConditions": [{ "Id": "condition", "Category": null, "Unknown": {} }]
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn round_trips_unknown_fields_and_nulls() {
+        let json = contract_json();
+        let contract = ContractFile::from_json_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&contract).unwrap(), json);
+
+        let contract = ContractFile::from_json_str(&json.to_string()).unwrap();
+        assert_eq!(serde_json::to_value(&contract).unwrap(), json);
+    }
+
+    #[test]
+    fn tells_null_apart_from_missing() {
+        let contract = ContractFile::from_json_value(contract_json()).unwrap();
+        let data = &contract.create_from_params.creation_data;
+        assert_eq!(data.description, Some(None));
+        assert_eq!(data.resource_id, None);
+        assert_eq!(data.contract_condition_ids, Some(None));
+    }
+
+    #[test]
+    fn reports_path_of_invalid_field() {
+        let mut json = contract_json();
+        json["CreateFromParamsJ"]["creationData"]["Targets"][0]["RepositoryId"] = json!(42);
+        for error in [
+            ContractFile::from_json_value(json.clone()).unwrap_err(),
+            ContractFile::from_json_str(&json.to_string()).unwrap_err(),
+        ] {
+            let error = error.to_string();
+            assert!(
+                error.contains("`CreateFromParamsJ.creationData.Targets[0].RepositoryId`"),
+                "{}",
+                error
+            );
+            assert!(error.contains("invalid type"), "{}", error);
+        }
+    }
+}
@@ -0,0 +1,387 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+use crate::auth::Account;
+
+pub const BEARER_ENV: &str = "HITMAN_BEARER";
+pub const USER_ID_ENV: &str = "HITMAN_USER_ID";
+
+/// Writes a file holding tokens so only the current user can read it.
+///
+/// On unix the file is created with mode 0600, and an existing file is narrowed to it.
+pub(crate) fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        let file = options.open(path)?;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        (&file).write_all(contents.as_bytes())
+    }
+    #[cfg(not(unix))]
+    {
+        options.open(path)?.write_all(contents.as_bytes())
+    }
+}
+
+/// Named accounts kept in the user config file,
+/// `<config dir>/hitman_contract_creator/profiles.json`:
+///
+/// ```json
+/// { "alt-account": { "bearer": "<token>", "user_id": "<optional>", "refresh_token": "<optional>" } }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Profiles {
+    path: PathBuf,
+    profiles: BTreeMap<String, Account>,
+}
+
+impl Profiles {
+    pub fn default_path() -> Option<PathBuf> {
+        Some(
+            dirs::config_dir()?
+                .join("hitman_contract_creator")
+                .join("profiles.json"),
+        )
+    }
+
+    /// Reads the profiles at `path`; a missing file has no profiles.
+    pub fn load(path: &Path) -> Result<Self> {
+        let profiles = match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .with_context(|| format!("Invalid profiles file {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read profiles file {}", path.display()))
+            }
+        };
+        Ok(Profiles {
+            path: path.to_path_buf(),
+            profiles,
+        })
+    }
+
+    pub fn load_default() -> Result<Self> {
+        Self::load(&Self::default_path().context("No user config directory found")?)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Account> {
+        self.profiles.get(name)
+    }
+
+    pub fn set(&mut self, name: &str, mut account: Account) {
+        account.name = None;
+        self.profiles.insert(name.to_string(), account);
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.profiles.keys()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        write_private(&self.path, &serde_json::to_string_pretty(&self.profiles)?)
+            .with_context(|| format!("Failed to write profiles file {}", self.path.display()))
+    }
+}
+
+/// Where to look for accounts. Sources are used in the order of the fields.
+#[derive(Debug, Clone, Default)]
+pub struct CredentialOptions {
+    /// Bearers given directly, e.g. on the command line.
+    pub bearers: Vec<String>,
+    /// Files holding nothing but a bearer.
+    pub bearer_files: Vec<String>,
+    /// Names of accounts in the profiles file.
+    pub profiles: Vec<String>,
+    /// A JSON list of accounts, see [`Account::load_all`].
+    pub accounts_file: Option<String>,
+    /// Ask for a bearer on the terminal when no other source gave one,
+    /// and fail if there's no terminal to ask on.
+    pub prompt: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Source {
+    Argument,
+    BearerFile(String),
+    Profile(String),
+    AccountsFile(String, usize),
+    Environment,
+    Prompt,
+}
+
+/// Accounts gathered from every credential source, remembering where each came from
+/// so rotated tokens can be written back.
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub accounts: Vec<Account>,
+    sources: Vec<Source>,
+}
+
+impl Credentials {
+    /// Collects accounts from `options`. Without any explicit source, falls back to
+    /// `HITMAN_BEARER`/`HITMAN_USER_ID` and then to a hidden prompt.
+    ///
+    /// Accounts without a name are named `#1`, `#2`, ... in the order found.
+    pub fn load(options: &CredentialOptions) -> Result<Self> {
+        Self::load_with_env(options, |name| std::env::var(name).ok())
+    }
+
+    /// [`Credentials::load`] reading environment variables through `env`.
+    pub fn load_with_env(
+        options: &CredentialOptions,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self> {
+        let mut accounts = vec![];
+        let mut sources = vec![];
+
+        for bearer in &options.bearers {
+            accounts.push(Account::new(bearer.clone()));
+            sources.push(Source::Argument);
+        }
+        for path in &options.bearer_files {
+            let bearer = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read bearer file {}", path))?;
+            accounts.push(Account::new(bearer.trim().to_string()));
+            sources.push(Source::BearerFile(path.clone()));
+        }
+        if !options.profiles.is_empty() {
+            let profiles = Profiles::load_default()?;
+            for name in &options.profiles {
+                let mut account = profiles.get(name).cloned().with_context(|| {
+                    format!("No profile {} in {}", name, profiles.path().display())
+                })?;
+                account.name = Some(name.clone());
+                accounts.push(account);
+                sources.push(Source::Profile(name.clone()));
+            }
+        }
+        if let Some(path) = &options.accounts_file {
+            for (i, account) in Account::load_all(path)?.into_iter().enumerate() {
+                accounts.push(account);
+                sources.push(Source::AccountsFile(path.clone(), i));
+            }
+        }
+
+        if accounts.is_empty() {
+            if let Some(bearer) = env(BEARER_ENV) {
+                let mut account = Account::new(bearer.trim().to_string());
+                account.user_id = env(USER_ID_ENV);
+                accounts.push(account);
+                sources.push(Source::Environment);
+            } else if options.prompt {
+                if !std::io::stdin().is_terminal() {
+                    bail!(
+                        "No bearer given, use --bearer-file, --profile, --accounts or {}",
+                        BEARER_ENV
+                    );
+                }
+                let bearer = rpassword::prompt_password("Bearer: ")?;
+                if bearer.trim().is_empty() {
+                    bail!("No bearer given");
+                }
+                accounts.push(Account::new(bearer.trim().to_string()));
+                sources.push(Source::Prompt);
+            }
+        }
+
+        for (i, account) in accounts.iter_mut().enumerate() {
+            account.name.get_or_insert_with(|| format!("#{}", i + 1));
+        }
+        Ok(Credentials { accounts, sources })
+    }
+
+    /// Writes the current bearers and refresh tokens back to the files they were read from.
+    ///
+    /// Accounts from the command line, the environment or the prompt aren't stored anywhere.
+    pub fn save_tokens(&self) -> Result<()> {
+        let mut profiles = None;
+        let mut accounts_files: BTreeMap<&str, Vec<Account>> = BTreeMap::new();
+
+        for (account, source) in self.accounts.iter().zip(&self.sources) {
+            match source {
+                Source::BearerFile(path) => write_private(Path::new(path), &account.bearer)
+                    .with_context(|| format!("Failed to write bearer file {}", path))?,
+                Source::Profile(name) => {
+                    if profiles.is_none() {
+                        profiles = Some(Profiles::load_default()?);
+                    }
+                    let profiles = profiles.as_mut().unwrap();
+                    if let Some(stored) = profiles.profiles.get_mut(name) {
+                        stored.bearer = account.bearer.clone();
+                        stored.refresh_token = account.refresh_token.clone();
+                    }
+                }
+                Source::AccountsFile(path, i) => {
+                    if !accounts_files.contains_key(path.as_str()) {
+                        accounts_files.insert(path, Account::load_all(path)?);
+                    }
+                    if let Some(stored) = accounts_files.get_mut(path.as_str()).unwrap().get_mut(*i)
+                    {
+                        stored.bearer = account.bearer.clone();
+                        stored.refresh_token = account.refresh_token.clone();
+                    }
+                }
+                Source::Argument | Source::Environment | Source::Prompt => {}
+            }
+        }
+
+        if let Some(profiles) = profiles {
+            profiles.save()?;
+        }
+        for (path, accounts) in accounts_files {
+            Account::save_all(path, &accounts)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory for the files of one test.
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hitman_credentials_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn account(bearer: &str) -> Account {
+        Account {
+            name: None,
+            bearer: bearer.to_string(),
+            user_id: Some("fe76faee-ecdc-4dd7-a6d5-c5b84054a87c".to_string()),
+            refresh_token: Some(format!("{}-refresh", bearer)),
+        }
+    }
+
+    #[cfg(unix)]
+    fn mode(path: &Path) -> u32 {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[test]
+    fn saves_and_loads_profiles() {
+        let path = temp_dir().join("config").join("profiles.json");
+        let mut profiles = Profiles::load(&path).unwrap();
+        assert_eq!(profiles.names().count(), 0);
+
+        let mut main = account("main-bearer");
+        main.name = Some("ignored".to_string());
+        profiles.set("main", main);
+        profiles.set("alt", account("alt-bearer"));
+        profiles.save().unwrap();
+        #[cfg(unix)]
+        assert_eq!(mode(&path), 0o600);
+
+        let profiles = Profiles::load(&path).unwrap();
+        assert_eq!(profiles.names().collect::<Vec<_>>(), ["alt", "main"]);
+        let main = profiles.get("main").unwrap();
+        assert_eq!(main.bearer, "main-bearer");
+        assert_eq!(main.refresh_token.as_deref(), Some("main-bearer-refresh"));
+        assert_eq!(main.name, None);
+        assert!(profiles.get("other").is_none());
+    }
+
+    #[test]
+    fn reads_environment_only_without_other_sources() {
+        let dir = temp_dir();
+        let bearer_file = dir.join("bearer.txt");
+        fs::write(&bearer_file, "file-bearer\n").unwrap();
+        let env = |name: &str| match name {
+            BEARER_ENV => Some(" env-bearer ".to_string()),
+            USER_ID_ENV => Some("fe76faee-ecdc-4dd7-a6d5-c5b84054a87c".to_string()),
+            _ => None,
+        };
+
+        let from_file = Credentials::load_with_env(
+            &CredentialOptions {
+                bearer_files: vec![bearer_file.to_string_lossy().to_string()],
+                ..Default::default()
+            },
+            env,
+        )
+        .unwrap();
+        let from_env = Credentials::load_with_env(&CredentialOptions::default(), env).unwrap();
+        let from_nothing =
+            Credentials::load_with_env(&CredentialOptions::default(), |_| None).unwrap();
+
+        assert_eq!(from_file.accounts.len(), 1);
+        assert_eq!(from_file.accounts[0].bearer, "file-bearer");
+        assert_eq!(from_file.accounts[0].user_id, None);
+        assert_eq!(from_file.accounts[0].name.as_deref(), Some("#1"));
+        assert_eq!(from_env.accounts.len(), 1);
+        assert_eq!(from_env.accounts[0].bearer, "env-bearer");
+        assert_eq!(
+            from_env.accounts[0].user_id.as_deref(),
+            Some("fe76faee-ecdc-4dd7-a6d5-c5b84054a87c")
+        );
+        assert!(from_nothing.accounts.is_empty());
+    }
+
+    #[test]
+    fn writes_rotated_tokens_back() {
+        let dir = temp_dir();
+        let bearer_file = dir.join("bearer.txt");
+        fs::write(&bearer_file, "old-file-bearer").unwrap();
+        let accounts_file = dir.join("accounts.json");
+        Account::save_all(
+            accounts_file.to_str().unwrap(),
+            &[account("first"), account("second")],
+        )
+        .unwrap();
+        let options = CredentialOptions {
+            bearers: vec!["argument-bearer".to_string()],
+            bearer_files: vec![bearer_file.to_string_lossy().to_string()],
+            accounts_file: Some(accounts_file.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+
+        let mut credentials = Credentials::load(&options).unwrap();
+        let names = credentials
+            .accounts
+            .iter()
+            .map(|a| a.label())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["#1", "#2", "#3", "#4"]);
+        for account in &mut credentials.accounts {
+            account.bearer = format!("new-{}", account.bearer);
+            account.refresh_token = Some("rotated".to_string());
+        }
+        credentials.save_tokens().unwrap();
+
+        assert_eq!(fs::read_to_string(&bearer_file).unwrap(), "new-old-file-bearer");
+        let stored = Account::load_all(accounts_file.to_str().unwrap()).unwrap();
+        assert_eq!(stored[0].bearer, "new-first");
+        assert_eq!(stored[1].bearer, "new-second");
+        assert_eq!(stored[1].refresh_token.as_deref(), Some("rotated"));
+        // Names given while loading aren't written to the file.
+        assert_eq!(stored[1].name, None);
+        #[cfg(unix)]
+        {
+            assert_eq!(mode(&bearer_file), 0o600);
+            assert_eq!(mode(&accounts_file), 0o600);
+        }
+
+        let reloaded = Credentials::load(&options).unwrap();
+        assert_eq!(reloaded.accounts[0].bearer, "argument-bearer");
+        assert_eq!(reloaded.accounts[1].bearer, "new-old-file-bearer");
+        assert_eq!(reloaded.accounts[3].bearer, "new-second");
+    }
+}
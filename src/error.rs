@@ -0,0 +1,81 @@
+use std::fmt;
+
+use crate::services::Endpoint;
+
+/// A request to a Hitman service that didn't get the expected answer.
+#[derive(Debug)]
+pub enum ServiceError {
+    /// The service answered with a non-success HTTP status.
+    Status {
+        endpoint: Endpoint,
+        status: reqwest::StatusCode,
+        body: String,
+    },
+    /// The service answered with a success status but a body we can't use.
+    InvalidResponse {
+        endpoint: Endpoint,
+        reason: String,
+        body: String,
+    },
+}
+
+impl ServiceError {
+    pub fn endpoint(&self) -> Endpoint {
+        match self {
+            ServiceError::Status { endpoint, .. } => *endpoint,
+            ServiceError::InvalidResponse { endpoint, .. } => *endpoint,
+        }
+    }
+
+    /// The HTTP status, if the service answered with an error status.
+    pub fn status(&self) -> Option<reqwest::StatusCode> {
+        match self {
+            ServiceError::Status { status, .. } => Some(*status),
+            ServiceError::InvalidResponse { .. } => None,
+        }
+    }
+
+    pub fn body(&self) -> &str {
+        match self {
+            ServiceError::Status { body, .. } => body,
+            ServiceError::InvalidResponse { body, .. } => body,
+        }
+    }
+
+    /// Whether the service turned the request down for the `Version` header the client sent.
+    ///
+    /// The service doesn't have a dedicated status for it, so this is a 400, 403, 412 or 426
+    /// whose body talks about the version.
+    pub fn is_version_mismatch(&self) -> bool {
+        match self {
+            ServiceError::Status { status, body, .. } => {
+                matches!(status.as_u16(), 400 | 403 | 412 | 426)
+                    && body.to_lowercase().contains("version")
+            }
+            ServiceError::InvalidResponse { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServiceError::Status {
+                endpoint,
+                status,
+                body,
+            } => write!(f, "{} failed with {}: {}", endpoint, status, body),
+            ServiceError::InvalidResponse {
+                endpoint,
+                reason,
+                body,
+            } => write!(
+                f,
+                "{} returned an invalid response ({}): {}",
+                endpoint, reason, body
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ServiceError {}
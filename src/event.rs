@@ -0,0 +1,562 @@
+//! Gameplay events sent to SaveEvents2 and their wire format.
+//!
+//! Every event is sent as an [`Envelope`]: the fields all events share, plus the `Name`
+//! and `Value` of its [`Payload`].
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::contract_file::Target;
+use crate::PublishTypes;
+
+const NO_ID: &str = "00000000-0000-0000-0000-000000000000";
+const ORIGIN: &str = "gameclient";
+const ORIGIN_POSITION: &str = "0.0, 0.0, 0.0";
+/// Broad kill methods of guns, whose kills are shots.
+const FIREARMS: &[&str] = &["pistol", "smg", "shotgun", "assaultrifle", "sniperrifle"];
+
+/// A gameplay event sent to SaveEvents2.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    ContractStartEvent,
+    IntroCutEndEvent,
+    /// Killing actor `repository_id` the way `conditions` ask for.
+    KillEvent {
+        repository_id: String,
+        conditions: KillConditions,
+    },
+    /// An actor knocked out. It is sent in the shape of a kill, but without lethal damage and
+    /// only marked as a target when the contract targets the actor.
+    PacifyEvent {
+        repository_id: String,
+    },
+    /// Changing into the outfit `repository_id`.
+    DisguiseEvent {
+        repository_id: String,
+    },
+    ItemPickedUpEvent {
+        repository_id: String,
+    },
+    /// Hitman was seen by the actors `repository_ids`.
+    SpottedEvent {
+        repository_ids: Vec<String>,
+    },
+    /// The body of actor `repository_id` was found.
+    BodyFoundEvent {
+        repository_id: String,
+    },
+    /// The body of actor `repository_id`, killed in an accident, was found.
+    AccidentBodyFoundEvent {
+        repository_id: String,
+    },
+    AllBodiesHiddenEvent,
+    /// A set piece of the level, e.g. a falling chandelier, was used.
+    SetpiecesEvent {
+        repository_id: String,
+        name: String,
+        setpiece_type: String,
+    },
+    ExitGateEvent,
+    ContractEndEvent,
+}
+
+/// How a target has to be killed, from its `Weapon` and `Outfit` in `CreateFromParamsJ`.
+///
+/// The default has no requirements and gives an unarmed kill in the suit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KillConditions {
+    /// E.g. `pistol`, `melee` or `accident`.
+    pub kill_method_broad: Option<String>,
+    /// E.g. `accident_drown` or `consumed_poison`.
+    pub kill_method_strict: Option<String>,
+    /// The weapon or item the kill has to be made with.
+    pub weapon_repository_id: Option<String>,
+    /// The disguise to wear, `None` for the suit or any disguise. A playthrough sets it to
+    /// the disguise already worn when any will do.
+    pub outfit_repository_id: Option<String>,
+}
+
+impl KillConditions {
+    pub fn of(target: &Target) -> Self {
+        // Explicit nulls count as left out.
+        let non_empty = |value: Option<&Option<String>>| {
+            value
+                .and_then(Option::as_ref)
+                .filter(|v| !v.is_empty())
+                .cloned()
+        };
+        let weapon = target.weapon.as_ref().and_then(Option::as_ref);
+        let outfit =
+            target.outfit.as_ref().and_then(Option::as_ref).filter(|o| {
+                o.required != Some(Some(false)) && o.is_hitman_suit != Some(Some(true))
+            });
+        KillConditions {
+            kill_method_broad: non_empty(weapon.and_then(|w| w.kill_method_broad.as_ref())),
+            kill_method_strict: non_empty(weapon.and_then(|w| w.kill_method_strict.as_ref())),
+            weapon_repository_id: non_empty(weapon.and_then(|w| w.repository_id.as_ref())),
+            outfit_repository_id: non_empty(outfit.and_then(|o| o.repository_id.as_ref())),
+        }
+    }
+
+    fn broad(&self) -> String {
+        self.kill_method_broad
+            .as_deref()
+            .unwrap_or_default()
+            .to_lowercase()
+    }
+
+    fn strict(&self) -> String {
+        self.kill_method_strict
+            .as_deref()
+            .unwrap_or_default()
+            .to_lowercase()
+    }
+
+    pub fn is_firearm(&self) -> bool {
+        FIREARMS.contains(&self.broad().as_str())
+            || self.strict() == "close_combat_pistol_elimination"
+    }
+
+    pub fn is_accident(&self) -> bool {
+        self.broad() == "accident" || self.strict().starts_with("accident_")
+    }
+
+    pub fn is_explosive(&self) -> bool {
+        self.broad() == "explosive" || self.strict() == "accident_explosion"
+    }
+
+    /// Only the headshot methods ask for a shot to the head, `..._elimination` just names
+    /// the weapon.
+    pub fn is_headshot(&self) -> bool {
+        self.strict().contains("headshot")
+    }
+
+    /// The `KillItemCategory` of the item the method kills with, empty without an item.
+    pub fn kill_item_category(&self) -> &'static str {
+        let broad = self.broad();
+        let strict = self.strict();
+        match broad.as_str() {
+            "pistol" => "pistol",
+            "smg" => "smg",
+            "shotgun" => "shotgun",
+            "assaultrifle" => "assaultrifle",
+            "sniperrifle" => "sniperrifle",
+            "explosive" => "explosives",
+            "melee" => "melee",
+            "fiberwire" => "fiberwire",
+            "poison" => "poison",
+            _ if strict == "close_combat_pistol_elimination" => "pistol",
+            _ if strict.ends_with("_poison") => "poison",
+            _ if strict == "fiberwire" => "fiberwire",
+            _ => "",
+        }
+    }
+}
+
+/// What the events of one playthrough share, from the contract and the session.
+#[derive(Debug, Clone)]
+pub struct EventContext<'a> {
+    pub game: PublishTypes,
+    pub contract_session_id: &'a str,
+    /// The mission the contract is created on.
+    pub contract_id: &'a str,
+    pub location_id: &'a str,
+    pub exit_id: &'a str,
+    pub game_changers: &'a [String],
+    pub user_id: &'a str,
+    pub session_id: &'a str,
+    /// The targets of the contract.
+    pub targets: &'a [Target],
+}
+
+impl Event {
+    /// Seconds into the playthrough the event is reported at.
+    fn timestamp(&self) -> f32 {
+        match self {
+            Event::ContractStartEvent => 0.0,
+            Event::ExitGateEvent => 2.0,
+            Event::ContractEndEvent => 3.0,
+            _ => 1.0,
+        }
+    }
+
+    pub fn payload(&self, context: &EventContext) -> Payload {
+        match self {
+            Event::ContractStartEvent => Payload::ContractStart(ContractStart {
+                loadout: vec![],
+                disguise: NO_ID.to_string(),
+                location_id: context.location_id.to_string(),
+                game_changers: context.game_changers.to_vec(),
+                contract_type: "creation".to_string(),
+                difficulty_level: 2.0,
+                is_hitman_suit: true,
+                // Picking a character came with HITMAN2's Sniper Assassin.
+                selected_character_id: (context.game != PublishTypes::HITMAN1)
+                    .then(|| NO_ID.to_string()),
+            }),
+            Event::IntroCutEndEvent => Payload::IntroCutEnd(String::new()),
+            Event::KillEvent {
+                repository_id,
+                conditions,
+            } => Payload::Kill(Kill::new(repository_id, conditions)),
+            Event::PacifyEvent { repository_id } => {
+                let is_target = context
+                    .targets
+                    .iter()
+                    .any(|t| t.repository_id.eq_ignore_ascii_case(repository_id));
+                Payload::Pacify(Kill::pacification(repository_id, is_target))
+            }
+            Event::DisguiseEvent { repository_id } => Payload::Disguise(repository_id.clone()),
+            Event::ItemPickedUpEvent { repository_id } => Payload::ItemPickedUp(ItemPickedUp {
+                repository_id: repository_id.clone(),
+                instance_id: uuid::Uuid::new_v4().to_string(),
+                item_type: String::new(),
+                item_value: 0.0,
+            }),
+            Event::SpottedEvent { repository_ids } => Payload::Spotted(repository_ids.clone()),
+            Event::BodyFoundEvent { repository_id } => {
+                Payload::BodyFound(BodyFound::new(repository_id))
+            }
+            Event::AccidentBodyFoundEvent { repository_id } => {
+                Payload::AccidentBodyFound(BodyFound::new(repository_id))
+            }
+            Event::AllBodiesHiddenEvent => Payload::AllBodiesHidden(String::new()),
+            Event::SetpiecesEvent {
+                repository_id,
+                name,
+                setpiece_type,
+            } => Payload::Setpieces(Setpieces {
+                repository_id: repository_id.clone(),
+                name: name.clone(),
+                helper: String::new(),
+                setpiece_type: setpiece_type.clone(),
+                tool_used: String::new(),
+                item_triggered: String::new(),
+                position: ORIGIN_POSITION.to_string(),
+            }),
+            Event::ExitGateEvent => Payload::ExitGate(ExitGate {
+                name: "Exit_Gate".to_string(),
+                repository_id: context.exit_id.to_string(),
+            }),
+            Event::ContractEndEvent => Payload::ContractEnd(String::new()),
+        }
+    }
+
+    /// The event as sent to SaveEvents2, with a new event ID.
+    pub fn envelope(&self, context: &EventContext) -> Envelope {
+        Envelope {
+            payload: self.payload(context),
+            contract_session_id: context.contract_session_id.to_string(),
+            contract_id: context.contract_id.to_string(),
+            timestamp: self.timestamp(),
+            user_id: context.user_id.to_string(),
+            session_id: context.session_id.to_string(),
+            origin: ORIGIN,
+            id: uuid::Uuid::new_v4().to_string(),
+            xbox: matches!(self, Event::ContractStartEvent).then_some(XboxMode {
+                xbox_game_mode: 2.0,
+                xbox_difficulty: 0.0,
+            }),
+        }
+    }
+}
+
+/// The fields every event carries, around its `Name` and `Value`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Envelope {
+    #[serde(flatten)]
+    pub payload: Payload,
+    pub contract_session_id: String,
+    pub contract_id: String,
+    pub timestamp: f32,
+    pub user_id: String,
+    pub session_id: String,
+    pub origin: &'static str,
+    pub id: String,
+    /// Only on ContractStart.
+    #[serde(flatten)]
+    pub xbox: Option<XboxMode>,
+}
+
+/// The `Name` of an event and its `Value`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "Name", content = "Value")]
+pub enum Payload {
+    ContractStart(ContractStart),
+    IntroCutEnd(String),
+    Kill(Kill),
+    Pacify(Kill),
+    /// The repository ID of the outfit.
+    Disguise(String),
+    ItemPickedUp(ItemPickedUp),
+    /// The repository IDs of the actors that saw Hitman.
+    Spotted(Vec<String>),
+    BodyFound(BodyFound),
+    AccidentBodyFound(BodyFound),
+    AllBodiesHidden(String),
+    #[serde(rename = "setpieces")]
+    Setpieces(Setpieces),
+    #[serde(rename = "exit_gate")]
+    ExitGate(ExitGate),
+    ContractEnd(String),
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct XboxMode {
+    pub xbox_game_mode: f32,
+    pub xbox_difficulty: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ContractStart {
+    pub loadout: Vec<Value>,
+    pub disguise: String,
+    pub location_id: String,
+    pub game_changers: Vec<String>,
+    pub contract_type: String,
+    pub difficulty_level: f32,
+    pub is_hitman_suit: bool,
+    /// Left out for HITMAN (2016).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selected_character_id: Option<String>,
+}
+
+/// The `Value` of Kill and Pacify events.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Kill {
+    pub repository_id: String,
+    pub actor_id: f32,
+    pub actor_type: f32,
+    pub kill_type: f32,
+    pub kill_context: f32,
+    pub body_part_id: f32,
+    pub room_id: f32,
+    pub explosion_type: f32,
+    pub total_damage: f32,
+    pub accident: bool,
+    pub explosive: bool,
+    pub projectile: bool,
+    pub sniper: bool,
+    pub is_headshot: bool,
+    pub is_target: bool,
+    pub through_wall: bool,
+    pub is_moving: bool,
+    pub outfit_is_hitman_suit: bool,
+    pub weapon_silenced: bool,
+    pub kill_item_repository_id: String,
+    pub outfit_repository_id: String,
+    pub actor_name: String,
+    pub kill_class: String,
+    pub actor_position: String,
+    pub hero_position: String,
+    pub damage_events: Vec<Value>,
+    pub player_id: f32,
+    pub kill_item_instance_id: String,
+    pub kill_item_category: String,
+    pub kill_method_broad: String,
+    pub kill_method_strict: String,
+    pub history: Vec<Value>,
+}
+
+impl Kill {
+    /// A kill of actor `repository_id` with every detail following from `conditions`.
+    pub fn new(repository_id: &str, conditions: &KillConditions) -> Self {
+        let firearm = conditions.is_firearm();
+        Kill {
+            repository_id: repository_id.to_string(),
+            actor_id: 0.0,
+            actor_type: 1.0,
+            kill_type: 1.0,
+            kill_context: 1.0,
+            body_part_id: 1.0,
+            room_id: 1.0,
+            explosion_type: 1.0,
+            total_damage: 1000.0,
+            accident: conditions.is_accident(),
+            explosive: conditions.is_explosive(),
+            projectile: firearm,
+            sniper: conditions.broad() == "sniperrifle",
+            is_headshot: conditions.is_headshot(),
+            is_target: true,
+            through_wall: false,
+            is_moving: false,
+            outfit_is_hitman_suit: conditions.outfit_repository_id.is_none(),
+            // No kill method asks for a silenced weapon, and a loud kill suits any of them.
+            weapon_silenced: false,
+            kill_item_repository_id: conditions.weapon_repository_id.clone().unwrap_or_default(),
+            outfit_repository_id: conditions.outfit_repository_id.clone().unwrap_or_default(),
+            actor_name: String::new(),
+            kill_class: if firearm { "ballistic" } else { "" }.to_string(),
+            actor_position: ORIGIN_POSITION.to_string(),
+            hero_position: ORIGIN_POSITION.to_string(),
+            damage_events: vec![],
+            player_id: 0.0,
+            kill_item_instance_id: String::new(),
+            kill_item_category: conditions.kill_item_category().to_string(),
+            kill_method_broad: conditions.kill_method_broad.clone().unwrap_or_default(),
+            kill_method_strict: conditions.kill_method_strict.clone().unwrap_or_default(),
+            history: vec![],
+        }
+    }
+
+    /// A knockout of actor `repository_id`, unarmed and in the suit.
+    pub fn pacification(repository_id: &str, is_target: bool) -> Self {
+        Kill {
+            total_damage: 0.0,
+            is_target,
+            ..Kill::new(repository_id, &KillConditions::default())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ItemPickedUp {
+    pub repository_id: String,
+    pub instance_id: String,
+    pub item_type: String,
+    pub item_value: f32,
+}
+
+/// The `Value` of BodyFound and AccidentBodyFound events.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BodyFound {
+    pub dead_body: DeadBody,
+}
+
+impl BodyFound {
+    pub fn new(repository_id: &str) -> Self {
+        BodyFound {
+            dead_body: DeadBody {
+                repository_id: repository_id.to_string(),
+                actor_id: 0.0,
+                is_crowd_actor: false,
+                death_context: 1.0,
+                death_type: 1.0,
+                kill_item_repository_id: String::new(),
+                kill_item_category: String::new(),
+                kill_method_broad: String::new(),
+                kill_method_strict: String::new(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeadBody {
+    pub repository_id: String,
+    pub actor_id: f32,
+    pub is_crowd_actor: bool,
+    pub death_context: f32,
+    pub death_type: f32,
+    pub kill_item_repository_id: String,
+    pub kill_item_category: String,
+    pub kill_method_broad: String,
+    pub kill_method_strict: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Setpieces {
+    #[serde(rename = "RepositoryId")]
+    pub repository_id: String,
+    #[serde(rename = "name_pq")]
+    pub name: String,
+    #[serde(rename = "setpieceHelper_pq")]
+    pub helper: String,
+    #[serde(rename = "setpieceType_pq")]
+    pub setpiece_type: String,
+    #[serde(rename = "toolUsed_pq")]
+    pub tool_used: String,
+    #[serde(rename = "Item_triggered_pq")]
+    pub item_triggered: String,
+    #[serde(rename = "Position")]
+    pub position: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExitGate {
+    #[serde(rename = "name_ExitGate")]
+    pub name: String,
+    #[serde(rename = "RepositoryId")]
+    pub repository_id: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conditions(broad: &str, strict: &str) -> KillConditions {
+        KillConditions {
+            kill_method_broad: Some(broad.to_string()),
+            kill_method_strict: Some(strict.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn shoots_without_headshot_or_silencer_unless_asked() {
+        let kill = Kill::new("target", &conditions("", "close_combat_pistol_elimination"));
+        assert!(kill.projectile);
+        assert!(!kill.is_headshot);
+        assert!(!kill.weapon_silenced);
+        assert_eq!(kill.kill_item_category, "pistol");
+
+        let kill = Kill::new("target", &conditions("pistol", "pistol_headshot"));
+        assert!(kill.is_headshot);
+        assert!(!kill.weapon_silenced);
+    }
+
+    #[test]
+    fn pacifies_without_lethal_damage() {
+        let kill = Kill::new("target", &KillConditions::default());
+        let pacification = Kill::pacification("guard", false);
+        assert_eq!(pacification.repository_id, "guard");
+        assert!(!pacification.is_target);
+        assert_eq!(pacification.total_damage, 0.0);
+        assert!(kill.total_damage > 0.0);
+        assert!(Kill::pacification("target", true).is_target);
+    }
+
+    #[test]
+    fn kills_by_accident() {
+        for (broad, strict) in [
+            ("accident", ""),
+            ("", "accident_drown"),
+            ("accident", "accident_push"),
+        ] {
+            let kill = Kill::new("target", &conditions(broad, strict));
+            assert!(kill.accident, "{} {}", broad, strict);
+            assert!(!kill.explosive);
+            assert!(!kill.projectile);
+            assert!(!kill.is_headshot);
+            assert_eq!(kill.kill_class, "");
+            assert_eq!(kill.kill_item_category, "");
+        }
+
+        let kill = Kill::new("target", &conditions("accident", "accident_explosion"));
+        assert!(kill.accident);
+        assert!(kill.explosive);
+    }
+
+    #[test]
+    fn kills_plainly_with_unknown_methods() {
+        let kill = Kill::new("target", &conditions("lasergun", "vaporize_elimination"));
+        assert!(!kill.accident);
+        assert!(!kill.explosive);
+        assert!(!kill.projectile);
+        assert!(!kill.sniper);
+        assert!(!kill.is_headshot);
+        assert!(!kill.weapon_silenced);
+        assert_eq!(kill.kill_class, "");
+        assert_eq!(kill.kill_item_category, "");
+        // Sent as given, so the service can still match them.
+        assert_eq!(kill.kill_method_broad, "lasergun");
+        assert_eq!(kill.kill_method_strict, "vaporize_elimination");
+    }
+}
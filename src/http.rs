@@ -0,0 +1,120 @@
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+use serde_json::Value;
+
+use anyhow::{Context, Result};
+
+use crate::error::ServiceError;
+use crate::retry::RetryPolicy;
+use crate::services::Endpoint;
+use crate::PublishTypes;
+
+/// Sends `request` and returns the response body, failing on any non-success status.
+///
+/// Failed attempts are repeated as `retry` allows. Every attempt is logged as one exchange.
+pub(crate) async fn send(
+    endpoint: Endpoint,
+    game: Option<PublishTypes>,
+    request: reqwest::RequestBuilder,
+    retry: &RetryPolicy,
+) -> Result<String> {
+    let mut attempt = 1;
+    let response = loop {
+        let started = Instant::now();
+        let response = request
+            .try_clone()
+            .context("Request can't be retried")?
+            .send()
+            .await;
+        log_exchange(endpoint, game, &response, started.elapsed(), attempt);
+        let wait = match &response {
+            Ok(response) if retry.should_retry(attempt, response.status()) => {
+                retry.backoff(attempt, Some(response.headers()))
+            }
+            Err(e) if attempt < retry.max_attempts && (e.is_connect() || e.is_timeout()) => {
+                retry.backoff(attempt, None)
+            }
+            _ => break response.with_context(|| format!("{} request failed", endpoint))?,
+        };
+        warn!(
+            "{} attempt {} of {} failed, retrying in {:?}",
+            endpoint, attempt, retry.max_attempts, wait
+        );
+        tokio::time::sleep(wait).await;
+        attempt += 1;
+    };
+    let status = response.status();
+    let body = response
+        .text()
+        .await
+        .with_context(|| format!("{} response could not be read", endpoint))?;
+    if !status.is_success() {
+        return Err(ServiceError::Status {
+            endpoint,
+            status,
+            body,
+        }
+        .into());
+    }
+    Ok(body)
+}
+
+fn log_exchange(
+    endpoint: Endpoint,
+    game: Option<PublishTypes>,
+    response: &reqwest::Result<reqwest::Response>,
+    latency: Duration,
+    attempt: u32,
+) {
+    let endpoint_name = endpoint.to_string();
+    let game = game.map(|game| format!("{:?}", game));
+    let latency_ms = latency.as_millis() as u64;
+    match response {
+        Ok(response) => info!(
+            endpoint = endpoint_name.as_str(),
+            status = response.status().as_u16(),
+            latency_ms = latency_ms,
+            attempt = attempt,
+            game = game.as_deref();
+            "{} responded {} in {} ms",
+            endpoint,
+            response.status(),
+            latency_ms
+        ),
+        Err(e) => info!(
+            endpoint = endpoint_name.as_str(),
+            latency_ms = latency_ms,
+            attempt = attempt,
+            game = game.as_deref();
+            "{} failed after {} ms: {}",
+            endpoint,
+            latency_ms,
+            e
+        ),
+    }
+}
+
+pub(crate) fn parse(endpoint: Endpoint, body: &str) -> Result<Value, ServiceError> {
+    serde_json::from_str(body).map_err(|e| ServiceError::InvalidResponse {
+        endpoint,
+        reason: e.to_string(),
+        body: body.to_string(),
+    })
+}
+
+pub(crate) fn field(
+    endpoint: Endpoint,
+    json: &Value,
+    pointer: &str,
+    body: &str,
+) -> Result<String, ServiceError> {
+    json.pointer(pointer)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| ServiceError::InvalidResponse {
+            endpoint,
+            reason: format!("{} not found", pointer),
+            body: body.to_string(),
+        })
+}
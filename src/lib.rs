@@ -0,0 +1,59 @@
+#![recursion_limit = "256"]
+
+//! Create whatever contract you want in HITMAN (2016), HITMAN2 and HITMAN3.
+//!
+//! ```no_run
+//! # async fn run() -> anyhow::Result<()> {
+//! use hitman_contract_creator::{Contract, PublishTypes, Services};
+//!
+//! let json = serde_json::from_str(&std::fs::read_to_string("contract.json")?)?;
+//! let mut contract = Contract::from_contract_json(json, PublishTypes::HITMAN3, Services::official(PublishTypes::HITMAN3))?;
+//! let published = contract.publish_contract(&"<user id>".to_string(), &"<bearer>".to_string()).await?;
+//! println!("{}", published.contract_public_id);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod auth;
+pub mod batch;
+pub mod client;
+pub mod contract;
+pub mod contract_file;
+pub mod credentials;
+pub mod error;
+pub mod event;
+mod http;
+pub mod logging;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod redact;
+pub mod retry;
+pub mod services;
+pub mod validate;
+pub mod version;
+
+pub use auth::Account;
+pub use client::ClientConfig;
+pub use contract::{Contract, PlannedRequest, PublishedContract};
+pub use contract_file::ContractFile;
+pub use credentials::{CredentialOptions, Credentials, Profiles};
+pub use error::ServiceError;
+pub use retry::RetryPolicy;
+pub use services::{Endpoint, Platform, Services, ServicesConfig};
+
+/// The game a contract gets published to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PublishTypes {
+    /// HITMAN (2016), on its original PC service.
+    HITMAN1,
+    HITMAN2,
+    HITMAN3,
+}
+
+impl PublishTypes {
+    pub const ALL: [PublishTypes; 3] = [
+        PublishTypes::HITMAN1,
+        PublishTypes::HITMAN2,
+        PublishTypes::HITMAN3,
+    ];
+}
@@ -0,0 +1,346 @@
+//! Logger setup of the command line tool.
+//!
+//! Every HTTP exchange is logged by the `hitman_contract_creator::http` target with the
+//! `endpoint`, `status`, `latency_ms`, `attempt` and `game` key-values; the JSON format
+//! writes them as fields of the record.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use log::kv::{self, Key, Value as KvValue, VisitSource, VisitValue};
+use log::LevelFilter;
+use serde_json::{json, Map, Value};
+
+use crate::redact::redact;
+
+tokio::task_local! {
+    /// Tag of the job the current task runs, e.g. `contract.json HITMAN3 main`. Log lines
+    /// of the task carry it, so those of jobs running at the same time can be told apart.
+    pub static JOB: String;
+}
+
+/// How log records are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// `[date][time][target][level] message`
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(anyhow!("Unknown log format: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    pub level: LevelFilter,
+    pub format: LogFormat,
+    /// Also write to this file. Left out, logs only go to stderr.
+    pub file: Option<PathBuf>,
+    /// The log file is rotated once it reaches this size, when opened and while logging.
+    pub max_file_size: u64,
+    /// Rotated files to keep, as `<file>.1` (newest) to `<file>.<keep>`.
+    pub keep_files: usize,
+    /// Log secrets and personal IDs as they are, see [`crate::redact`].
+    pub unredacted: bool,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            level: LevelFilter::Info,
+            format: LogFormat::Text,
+            file: None,
+            max_file_size: 10 * 1024 * 1024,
+            keep_files: 3,
+            unredacted: false,
+        }
+    }
+}
+
+/// The level `verbose` `-v` and `quiet` `-q` flags turn the default Info level into.
+pub fn level_from_flags(verbose: u8, quiet: u8) -> LevelFilter {
+    let levels = [
+        LevelFilter::Off,
+        LevelFilter::Error,
+        LevelFilter::Warn,
+        LevelFilter::Info,
+        LevelFilter::Debug,
+        LevelFilter::Trace,
+    ];
+    let level = (3 + verbose as isize - quiet as isize).clamp(0, levels.len() as isize - 1);
+    levels[level as usize]
+}
+
+/// Installs the global logger.
+pub fn init(config: &LogConfig) -> Result<()> {
+    let format = config.format;
+    let unredacted = config.unredacted;
+    let mut dispatch = fern::Dispatch::new()
+        .format(move |out, message, record| {
+            let mut message = message.to_string();
+            if !unredacted {
+                message = redact(&message);
+            }
+            let job = JOB.try_with(Clone::clone).ok();
+            match format {
+                LogFormat::Text => out.finish(format_args!(
+                    "{}[{}][{}]{} {}",
+                    chrono::Local::now().format("[%Y-%m-%d][%H:%M:%S]"),
+                    record.target(),
+                    record.level(),
+                    job.map(|job| format!("[{}]", job)).unwrap_or_default(),
+                    message
+                )),
+                LogFormat::Json => {
+                    out.finish(format_args!("{}", json_record(record, message, job)))
+                }
+            }
+        })
+        .level(config.level)
+        .level_for("reqwest", config.level.min(LevelFilter::Info))
+        .level_for("hyper", config.level.min(LevelFilter::Info))
+        .chain(std::io::stderr());
+
+    if let Some(path) = &config.file {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create log directory {}", dir.display()))?;
+        }
+        let file = RotatingFile::open(path, config.max_file_size, config.keep_files)
+            .with_context(|| format!("Failed to open log file {}", path.display()))?;
+        dispatch = dispatch.chain(Box::new(file) as Box<dyn Write + Send>);
+    }
+    dispatch.apply()?;
+    Ok(())
+}
+
+/// A record in the JSON format: its level, target, message and job, plus every key-value.
+fn json_record(record: &log::Record, message: String, job: Option<String>) -> Value {
+    let mut fields = Map::new();
+    fields.insert("timestamp".into(), json!(chrono::Utc::now().to_rfc3339()));
+    fields.insert("level".into(), json!(record.level().to_string()));
+    fields.insert("target".into(), json!(record.target()));
+    fields.insert("message".into(), json!(message));
+    if let Some(job) = job {
+        fields.insert("job".into(), json!(job));
+    }
+    record.key_values().visit(&mut JsonFields(&mut fields)).ok();
+    Value::Object(fields)
+}
+
+/// The log file, rotated once it reaches `max_size`.
+///
+/// fern flushes after every record, so rotating on flush never splits a record in two.
+struct RotatingFile {
+    path: PathBuf,
+    /// Only `None` while the file is being rotated.
+    file: Option<fs::File>,
+    size: u64,
+    max_size: u64,
+    keep: usize,
+}
+
+impl RotatingFile {
+    fn open(path: &Path, max_size: u64, keep: usize) -> io::Result<Self> {
+        rotate(path, max_size, keep)?;
+        let file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(RotatingFile {
+            path: path.to_path_buf(),
+            size: file.metadata()?.len(),
+            file: Some(file),
+            max_size,
+            keep,
+        })
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let file = self.file.as_mut().ok_or(io::ErrorKind::NotFound)?;
+        let written = file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(file) = &mut self.file {
+            file.flush()?;
+        }
+        if self.size >= self.max_size {
+            // Closed first, an open file can't be renamed on Windows.
+            self.file = None;
+            let reopened = RotatingFile::open(&self.path, self.max_size, self.keep)?;
+            *self = reopened;
+        }
+        Ok(())
+    }
+}
+
+/// Moves `path` to `<path>.1`, `<path>.1` to `<path>.2` and so on once it reaches `max_size`,
+/// dropping the oldest file beyond `keep`.
+fn rotate(path: &Path, max_size: u64, keep: usize) -> std::io::Result<()> {
+    let size = match fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    if size < max_size {
+        return Ok(());
+    }
+    if keep == 0 {
+        return fs::remove_file(path);
+    }
+    let rotated = |n: usize| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    };
+    for n in (1..keep).rev() {
+        if rotated(n).exists() {
+            fs::rename(rotated(n), rotated(n + 1))?;
+        }
+    }
+    fs::rename(path, rotated(1))
+}
+
+struct JsonFields<'a>(&'a mut Map<String, Value>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: KvValue<'kvs>) -> Result<(), kv::Error> {
+        let mut json = JsonValue(Value::Null);
+        value.visit(&mut json)?;
+        self.0.insert(key.to_string(), json.0);
+        Ok(())
+    }
+}
+
+struct JsonValue(Value);
+
+impl<'v> VisitValue<'v> for JsonValue {
+    fn visit_any(&mut self, value: KvValue) -> Result<(), kv::Error> {
+        self.0 = json!(value.to_string());
+        Ok(())
+    }
+
+    fn visit_null(&mut self) -> Result<(), kv::Error> {
+        self.0 = Value::Null;
+        Ok(())
+    }
+
+    fn visit_u64(&mut self, value: u64) -> Result<(), kv::Error> {
+        self.0 = json!(value);
+        Ok(())
+    }
+
+    fn visit_i64(&mut self, value: i64) -> Result<(), kv::Error> {
+        self.0 = json!(value);
+        Ok(())
+    }
+
+    fn visit_f64(&mut self, value: f64) -> Result<(), kv::Error> {
+        self.0 = json!(value);
+        Ok(())
+    }
+
+    fn visit_bool(&mut self, value: bool) -> Result<(), kv::Error> {
+        self.0 = json!(value);
+        Ok(())
+    }
+
+    fn visit_str(&mut self, value: &str) -> Result<(), kv::Error> {
+        self.0 = json!(value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hitman_logging_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn log_line(file: &mut RotatingFile, line: &str) {
+        writeln!(file, "{}", line).unwrap();
+        file.flush().unwrap();
+    }
+
+    #[test]
+    fn rotates_while_logging() {
+        let path = temp_dir().join("run.log");
+        let rotated = |n: usize| PathBuf::from(format!("{}.{}", path.display(), n));
+        let mut file = RotatingFile::open(&path, 20, 2).unwrap();
+
+        log_line(&mut file, "first record");
+        assert!(!rotated(1).exists());
+        log_line(&mut file, "second record");
+        assert_eq!(fs::read_to_string(rotated(1)).unwrap(), "first record\nsecond record\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+
+        for record in ["third record", "fourth record", "fifth record", "sixth record"] {
+            log_line(&mut file, record);
+        }
+        // Only `keep` rotated files stay, and no record is split across files.
+        assert_eq!(fs::read_to_string(rotated(1)).unwrap(), "fifth record\nsixth record\n");
+        assert_eq!(fs::read_to_string(rotated(2)).unwrap(), "third record\nfourth record\n");
+        assert!(!rotated(3).exists());
+    }
+
+    #[test]
+    fn rotates_large_file_when_opened() {
+        let path = temp_dir().join("run.log");
+        fs::write(&path, "x".repeat(100)).unwrap();
+        let mut file = RotatingFile::open(&path, 50, 1).unwrap();
+        log_line(&mut file, "new run");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new run\n");
+        assert_eq!(fs::read_to_string(format!("{}.1", path.display())).unwrap().len(), 100);
+    }
+
+    #[test]
+    fn writes_exchange_key_values_as_json_fields() {
+        let kvs: &[(&str, log::kv::Value)] = &[
+            ("endpoint", "GetForPlay2".into()),
+            ("status", 200u16.into()),
+            ("latency_ms", 42u64.into()),
+            ("attempt", 1u32.into()),
+            ("game", "HITMAN3".into()),
+        ];
+        let record = log::Record::builder()
+            .level(log::Level::Info)
+            .target("hitman_contract_creator::http")
+            .key_values(&kvs)
+            .build();
+        let json = json_record(
+            &record,
+            "GetForPlay2 responded 200 OK in 42 ms".to_string(),
+            Some("contract.json HITMAN3 main".to_string()),
+        );
+
+        assert_eq!(json["level"], "INFO");
+        assert_eq!(json["target"], "hitman_contract_creator::http");
+        assert_eq!(json["message"], "GetForPlay2 responded 200 OK in 42 ms");
+        assert_eq!(json["job"], "contract.json HITMAN3 main");
+        assert_eq!(json["endpoint"], "GetForPlay2");
+        assert_eq!(json["status"], 200);
+        assert_eq!(json["latency_ms"], 42);
+        assert_eq!(json["attempt"], 1);
+        assert_eq!(json["game"], "HITMAN3");
+        assert!(json["timestamp"].is_string());
+    }
+}
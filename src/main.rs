@@ -1,15 +1,27 @@
 #![recursion_limit = "256"]
 
 use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use log::{debug, info, trace, warn};
-mod contract;
+use chrono::Utc;
+use log::{error, info, warn};
 
 use clap::{Parser, Subcommand};
-use serde_json::Value;
-use tokio::{runtime::Handle, task::block_in_place};
+use tokio::sync::Semaphore;
 
-use crate::contract::Contract;
+use anyhow::Context;
+use hitman_contract_creator::auth::check_online;
+use hitman_contract_creator::batch::{report, BatchOutcome, JobResult, OutputFormat};
+use hitman_contract_creator::logging::{self, LogConfig, LogFormat};
+use hitman_contract_creator::services::HeaderProfile;
+use hitman_contract_creator::validate::validate;
+use hitman_contract_creator::version::{probe_version, VersionCache};
+use hitman_contract_creator::{
+    Account, ClientConfig, Contract, ContractFile, CredentialOptions, Credentials, Platform, Profiles,
+    PublishTypes, RetryPolicy, Services, ServicesConfig,
+};
 
 // Hitman Contract Submitter
 #[derive(Parser, Debug)]
@@ -17,11 +29,212 @@ use crate::contract::Contract;
 struct Args {
     #[clap(subcommand)]
     command: Commands,
+
+    /// Log bearers, user IDs and session IDs as they are instead of masking them
+    #[clap(long, global = true)]
+    unredacted_logs: bool,
+
+    /// Log more, -vv for everything
+    #[clap(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Log less, -qq for errors only
+    #[clap(short, long, global = true, action = clap::ArgAction::Count)]
+    quiet: u8,
+
+    /// Also write the log to this file
+    #[clap(long, global = true, value_parser)]
+    log_file: Option<PathBuf>,
+
+    /// `text` or `json`, one object per line with a record per HTTP exchange
+    #[clap(long, global = true, value_parser, default_value = "text")]
+    log_format: LogFormat,
+
+    /// Rotate the log file once it reaches this many megabytes
+    #[clap(long, global = true, value_parser, default_value_t = 10)]
+    log_max_mb: u64,
+
+    /// Rotated log files to keep
+    #[clap(long, global = true, value_parser, default_value_t = 3)]
+    log_keep: usize,
+}
+#[derive(Debug, clap::Args)]
+struct AccountArgs {
+    // Bearer for auth reasons. Other users of the machine can see it, prefer the other sources.
+    #[clap(long,short , value_parser)]
+    bearer: Vec<String>,
+
+    /// File holding a bearer
+    #[clap(long, value_parser)]
+    bearer_file: Vec<String>,
+
+    /// Named account from the profiles file in the user config directory
+    #[clap(long, value_parser)]
+    profile: Vec<String>,
+
+    /// JSON file pairing bearers with user IDs
+    #[clap(long, value_parser)]
+    accounts: Option<String>,
+}
+
+impl AccountArgs {
+    /// Every account given. Falls back to HITMAN_BEARER and then, if `prompt`, asks for a bearer.
+    fn load(&self, prompt: bool) -> anyhow::Result<Credentials> {
+        Credentials::load(&CredentialOptions {
+            bearers: self.bearer.clone(),
+            bearer_files: self.bearer_file.clone(),
+            profiles: self.profile.clone(),
+            accounts_file: self.accounts.clone(),
+            prompt,
+        })
+    }
+}
+
+#[derive(Debug, clap::Args)]
+struct ServiceArgs {
+    /// JSON file with the service endpoints to use per game
+    #[clap(long, value_parser)]
+    services: Option<String>,
+
+    /// Base url of the HITMAN (2016) service, e.g. a local server
+    #[clap(long, value_parser)]
+    hitman1_url: Option<String>,
+
+    /// Base url of the HITMAN2 service, e.g. a local server
+    #[clap(long, value_parser)]
+    hitman2_url: Option<String>,
+
+    /// Base url of the HITMAN3 service, e.g. a local server
+    #[clap(long, value_parser)]
+    hitman3_url: Option<String>,
+
+    /// Store of the accounts: steam, epic, xbox or playstation. Read from each bearer when left out
+    #[clap(long, value_parser)]
+    platform: Option<Platform>,
+}
+
+/// The services config and the `--platform` it is used for.
+struct ServiceSelection {
+    config: ServicesConfig,
+    platform: Option<Platform>,
+}
+
+impl ServiceSelection {
+    /// `--platform`, or else the platform of the bearer of `account`.
+    fn platform_of(&self, account: &Account) -> Option<Platform> {
+        self.platform.or_else(|| account.known_platform())
+    }
+
+    fn services(&self, game: PublishTypes, platform: Option<Platform>) -> anyhow::Result<Services> {
+        self.config.services_on(game, platform)
+    }
+}
+
+impl ServiceArgs {
+    fn load(self) -> anyhow::Result<ServiceSelection> {
+        let mut services = match self.services {
+            Some(path) => ServicesConfig::from_file(&path)?,
+            None => ServicesConfig::default(),
+        };
+        // Only the base url is replaced, endpoints and headers from --services stay.
+        if let Some(url) = self.hitman1_url {
+            services.entry_mut(PublishTypes::HITMAN1).base_url = url.trim_end_matches('/').to_string();
+        }
+        if let Some(url) = self.hitman2_url {
+            services.entry_mut(PublishTypes::HITMAN2).base_url = url.trim_end_matches('/').to_string();
+        }
+        if let Some(url) = self.hitman3_url {
+            services.entry_mut(PublishTypes::HITMAN3).base_url = url.trim_end_matches('/').to_string();
+        }
+        // Versions found by probe-version, unless --services sets one.
+        match VersionCache::load_default() {
+            Ok(cache) => cache.apply(&mut services),
+            Err(e) => warn!("Ignoring the version cache: {:#}", e),
+        }
+        Ok(ServiceSelection {
+            config: services,
+            platform: self.platform,
+        })
+    }
+}
+
+#[derive(Debug, clap::Args)]
+struct ClientArgs {
+    /// Seconds to wait for a connection to the service
+    #[clap(long, value_parser, default_value_t = 10)]
+    connect_timeout: u64,
+
+    /// Seconds a whole request may take, including reading the response
+    #[clap(long, value_parser, default_value_t = 60)]
+    timeout: u64,
+
+    /// Proxy for every request, e.g. http://127.0.0.1:8888 or socks5://127.0.0.1:1080
+    #[clap(long, value_parser)]
+    proxy: Option<String>,
+
+    /// Extra CA certificate to trust, PEM or DER
+    #[clap(long, value_parser)]
+    ca_cert: Vec<PathBuf>,
+}
+
+impl ClientArgs {
+    fn build(self) -> anyhow::Result<reqwest::Client> {
+        ClientConfig {
+            connect_timeout: Duration::from_secs(self.connect_timeout),
+            timeout: Duration::from_secs(self.timeout),
+            proxy: self.proxy,
+            ca_certs: self.ca_cert,
+        }
+        .build()
+    }
 }
-#[derive(Debug, Clone, Copy)]
-pub enum PublishTypes {
-    HITMAN2,
-    HITMAN3,
+
+#[derive(Debug, Subcommand)]
+enum AuthCommands {
+    /// Decode bearers locally and show who they belong to and when they expire
+    Check {
+        #[clap(flatten)]
+        accounts: AccountArgs,
+
+        /// Also ask the service whether it accepts the bearer
+        #[clap(long)]
+        online: bool,
+
+        // Check against hitman (2016), defaults to the game of the token
+        #[clap(long)]
+        hitman1: bool,
+
+        // Check against hitman2, defaults to the game of the token
+        #[clap(long)]
+        hitman2: bool,
+
+        // Check against hitman3, defaults to the game of the token
+        #[clap(long)]
+        hitman3: bool,
+
+        #[clap(flatten)]
+        services: ServiceArgs,
+
+        #[clap(flatten)]
+        client: ClientArgs,
+    },
+    /// Store a bearer under a name, to be used with --profile
+    SaveProfile {
+        /// Name of the profile, e.g. alt-account
+        #[clap(value_parser)]
+        name: String,
+
+        #[clap(flatten)]
+        accounts: AccountArgs,
+
+        /// The User's ID, read from the bearer when left out
+        #[clap(long, value_parser)]
+        user_id: Option<String>,
+
+        /// Refresh token to renew the bearer with
+        #[clap(long, value_parser)]
+        refresh_token: Option<String>,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -32,12 +245,15 @@ enum Commands {
         /// The file to submit
         #[clap(value_parser, short, long)]
         file: Vec<String>,
-        // The User's ID
-        #[clap(value_parser,default_value="fe76faee-ecdc-4dd7-a6d5-c5b84054a87c")]
-        userid: String,
-        // Bearer for auth reasons.
-        #[clap(long,short , value_parser)]
-        bearer: Vec<String>,
+        // The User's ID, read from the bearer when left out. Only allowed with a single bearer.
+        #[clap(value_parser)]
+        userid: Option<String>,
+        #[clap(flatten)]
+        accounts: AccountArgs,
+
+        // Publish to hitman (2016)
+        #[clap(long)]
+        hitman1: bool,
 
         // Publish to hitman2
         #[clap(long)]
@@ -46,66 +262,630 @@ enum Commands {
         // Publish to hitman3
         #[clap(long)]
         hitman3: bool,
+
+        #[clap(flatten)]
+        services: ServiceArgs,
+
+        #[clap(flatten)]
+        client: ClientArgs,
+
+        /// Print every request that would be sent instead of sending it
+        #[clap(long)]
+        dry_run: bool,
+
+        /// Write the dry run requests to `<file>.<game>.json` in this directory instead of stdout,
+        /// `<file>.<game>.<n>.json` for the n-th of several accounts
+        #[clap(long, value_parser, requires = "dry-run")]
+        dry_run_dir: Option<String>,
+
+        /// Attempts per request on 429/5xx responses, including the first one
+        #[clap(long, value_parser, default_value_t = 4)]
+        max_attempts: u32,
+
+        /// Backoff before the first retry in milliseconds, doubled on every retry
+        #[clap(long, value_parser, default_value_t = 500)]
+        backoff_ms: u64,
+
+        /// Random spread of each backoff, from 0.0 to 1.0
+        #[clap(long, value_parser, default_value_t = 0.2)]
+        jitter: f64,
+
+        /// Also retry SaveEvents2 and CreateFromParams. A retry may create a contract twice
+        #[clap(long)]
+        retry_non_idempotent: bool,
+
+        /// Report the results on stdout as a `table`, `json` or `csv`
+        #[clap(long, value_parser, default_value = "table")]
+        output: OutputFormat,
+
+        /// Jobs (file, game, account) to publish at the same time
+        #[clap(long, short, value_parser, default_value_t = 1)]
+        jobs: usize,
+
+        /// Jobs of the same account to publish at the same time
+        #[clap(long, value_parser, default_value_t = 1)]
+        jobs_per_account: usize,
     },
+    /// Check contract files without publishing them
+    #[clap(arg_required_else_help = true)]
+    Validate {
+        /// The files to check
+        #[clap(value_parser, short, long)]
+        file: Vec<String>,
+
+        // Check against hitman (2016), defaults to any game
+        #[clap(long)]
+        hitman1: bool,
+
+        // Check against hitman2, defaults to any game
+        #[clap(long)]
+        hitman2: bool,
+
+        // Check against hitman3, defaults to any game
+        #[clap(long)]
+        hitman3: bool,
+    },
+    /// Find a game version the service accepts and remember it for later runs
+    ProbeVersion {
+        #[clap(flatten)]
+        accounts: AccountArgs,
+
+        // Probe hitman (2016), defaults to every game
+        #[clap(long)]
+        hitman1: bool,
+
+        // Probe hitman2, defaults to every game
+        #[clap(long)]
+        hitman2: bool,
+
+        // Probe hitman3, defaults to every game
+        #[clap(long)]
+        hitman3: bool,
+
+        /// Version to try before the configured ones, e.g. 8.20.0
+        #[clap(long = "try", value_parser)]
+        try_versions: Vec<String>,
+
+        #[clap(flatten)]
+        services: ServiceArgs,
+
+        #[clap(flatten)]
+        client: ClientArgs,
+    },
+    /// Inspect bearer tokens
+    Auth {
+        #[clap(subcommand)]
+        command: AuthCommands,
+    },
+}
+
+/// The games of the `--hitman1`, `--hitman2` and `--hitman3` flags given, in that order.
+fn selected_games(hitman1: bool, hitman2: bool, hitman3: bool) -> Vec<PublishTypes> {
+    PublishTypes::ALL
+        .into_iter()
+        .zip([hitman1, hitman2, hitman3])
+        .filter_map(|(game, selected)| selected.then_some(game))
+        .collect()
+}
+
+/// An account of the batch, shared by all of its jobs.
+struct SharedAccount {
+    /// Where it is in `Credentials::accounts`.
+    index: usize,
+    account: tokio::sync::Mutex<Account>,
+    /// The slots of the account, so it doesn't run more than `--jobs-per-account` jobs at once.
+    jobs: Semaphore,
+}
+
+/// Publishes `contract` with `account` once both a slot of the whole batch and one of
+/// the account are free, and saves the bearer if it was refreshed on the way.
+async fn publish_job(
+    mut contract: Contract,
+    file: String,
+    game: PublishTypes,
+    account: Arc<SharedAccount>,
+    credentials: Arc<Mutex<Credentials>>,
+    all_jobs: Arc<Semaphore>,
+) -> JobResult {
+    // The account's slot first, so jobs waiting on a busy account don't block other accounts.
+    let _account_permit = account.jobs.acquire().await.expect("job semaphore closed");
+    let _permit = all_jobs.acquire().await.expect("job semaphore closed");
+
+    let started_at = Utc::now();
+    // Jobs of the same account share it, so only one of them refreshes an expired bearer.
+    let result = contract.publish_contract_shared(&account.account).await;
+    let finished_at = Utc::now();
+    let index = account.index;
+    let account = account.account.lock().await.clone();
+    {
+        let mut credentials = credentials.lock().unwrap();
+        if credentials.accounts[index].bearer != account.bearer {
+            credentials.accounts[index] = account.clone();
+            if let Err(e) = credentials.save_tokens() {
+                warn!("Failed to save the refreshed bearers: {:#}", e);
+            }
+        }
+    }
+    if let Err(e) = &result {
+        warn!("Publishing {} [{:?}] with account {} failed: {:#}", file, game, account.label(), e);
+    }
+    JobResult {
+        file,
+        game,
+        account: account.label(),
+        started_at,
+        finished_at,
+        result: result.map_err(|e| format!("{:#}", e)),
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     env::set_var("RUST_BACKTRACE", "1");
-
-    fern::Dispatch::new()
-        // Perform allocation-free log formatting
-        .format(|out, message, record| {
-            out.finish(format_args!(
-                "{}[{}][{}] {}",
-                chrono::Local::now().format("[%Y-%m-%d][%H:%M:%S]"),
-                record.target(),
-                record.level(),
-                message
-            ))
-        })
-        // Add blanket level filter -
-        .level(log::LevelFilter::Debug)
-        // - and per-module overrides
-        .level_for("reqwest", log::LevelFilter::Info)
-        // Output to stdout, files, and other Dispatch configurations
-        .chain(std::io::stdout())
-        .chain(fern::log_file("output.log")?)
-        .apply()?;
+    dotenvy::dotenv().ok();
 
     let args = Args::parse();
 
+    logging::init(&LogConfig {
+        level: logging::level_from_flags(args.verbose, args.quiet),
+        format: args.log_format,
+        file: args.log_file.clone(),
+        max_file_size: args.log_max_mb * 1024 * 1024,
+        keep_files: args.log_keep,
+        unredacted: args.unredacted_logs,
+    })?;
+
     use std::fs;
     match args.command {
         Commands::Publish {
             file,
-            bearer,
             userid,
+            accounts,
+            hitman1,
             hitman2,
             hitman3,
+            services,
+            client,
+            dry_run,
+            dry_run_dir,
+            max_attempts,
+            backoff_ms,
+            jitter,
+            retry_non_idempotent,
+            output,
+            jobs,
+            jobs_per_account,
         } => {
-            let mut contractids=vec![];
+            let retry = RetryPolicy {
+                max_attempts: max_attempts.max(1),
+                initial_backoff: Duration::from_millis(backoff_ms),
+                jitter,
+                ..Default::default()
+            };
+            let non_idempotent_retry = if retry_non_idempotent {
+                retry.clone()
+            } else {
+                RetryPolicy::none()
+            };
+
+            let games = selected_games(hitman1, hitman2, hitman3);
+            if file.is_empty() || games.is_empty() {
+                error!("Nothing to publish, give --file and at least one of --hitman1, --hitman2 and --hitman3");
+                std::process::exit(BatchOutcome::NoJobs.exit_code());
+            }
+
+            let services = services.load()?;
+            let client = client.build()?;
+            let mut credentials = accounts.load(!dry_run)?;
+            let accounts = &mut credentials.accounts;
+            if let Some(userid) = userid {
+                match accounts.as_mut_slice() {
+                    [account] => account.user_id = Some(userid),
+                    [] if dry_run => accounts.push(Account {
+                        name: Some("#1".to_string()),
+                        bearer: String::new(),
+                        user_id: Some(userid),
+                        refresh_token: None,
+                    }),
+                    _ => {
+                        return Err("A user ID can only be given with a single bearer, pair bearers with user IDs in --accounts instead".into())
+                    }
+                }
+            }
+
+            let all_jobs = Arc::new(Semaphore::new(jobs.max(1)));
+            let labels = credentials
+                .accounts
+                .iter()
+                .map(Account::label)
+                .collect::<Vec<_>>();
+            let platforms = credentials
+                .accounts
+                .iter()
+                .map(|account| services.platform_of(account))
+                .collect::<Vec<_>>();
+            let shared_accounts = credentials
+                .accounts
+                .iter()
+                .enumerate()
+                .map(|(index, account)| {
+                    Arc::new(SharedAccount {
+                        index,
+                        account: tokio::sync::Mutex::new(account.clone()),
+                        jobs: Semaphore::new(jobs_per_account.max(1)),
+                    })
+                })
+                .collect::<Vec<_>>();
+            let credentials = Arc::new(Mutex::new(credentials));
+
+            // Every job is spawned right away and waits for its permits, the handles keep the input order.
+            // With --dry-run, one plan per account, each through the service of its own store.
+            let mut dry_run_accounts = vec![];
+            if dry_run {
+                dry_run_accounts = credentials
+                    .lock()
+                    .unwrap()
+                    .accounts
+                    .iter()
+                    .map(|account| {
+                        let userid = account
+                            .user_id()
+                            .unwrap_or_else(|_| "<UserId>".to_string());
+                        (account.label(), userid, services.platform_of(account))
+                    })
+                    .collect::<Vec<_>>();
+                if dry_run_accounts.is_empty() {
+                    dry_run_accounts.push(("no account".to_string(), "<UserId>".to_string(), None));
+                }
+            }
+            // How each (file, game, account) of a dry run went.
+            let mut dry_runs: Vec<bool> = vec![];
+
+            let mut handles = vec![];
             for file in file {
-                let contract = fs::read_to_string(&file)?;
-                let contract: Value = serde_json::from_str(contract.as_str())?;
-
-                if hitman2 {
-                    let mut contract =
-                        Contract::from_contract_json(contract.clone(), PublishTypes::HITMAN2)?;
-                    for bearer in &bearer {
-                        contractids.push((file.clone(),PublishTypes::HITMAN2,contract.publish_contract(&userid, &bearer).await?));
+                let contract = fs::read_to_string(&file)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|contract| ContractFile::from_json_str(contract.as_str()))
+                    .with_context(|| format!("Failed to parse {}", file));
+                let contract = match contract {
+                    Ok(contract) => contract,
+                    Err(e) => {
+                        warn!("{:#}", e);
+                        if dry_run {
+                            dry_runs.extend(vec![false; games.len() * dry_run_accounts.len()]);
+                            continue;
+                        }
+                        let now = Utc::now();
+                        for game in &games {
+                            for label in &labels {
+                                handles.push(tokio::spawn(std::future::ready(JobResult {
+                                    file: file.clone(),
+                                    game: *game,
+                                    account: label.clone(),
+                                    started_at: now,
+                                    finished_at: now,
+                                    result: Err(format!("{:#}", e)),
+                                })));
+                            }
+                        }
+                        continue;
+                    }
+                };
+
+                for game in &games {
+                    let contract_for = |platform: Option<Platform>| -> anyhow::Result<Contract> {
+                        Ok(Contract::from_contract_file(
+                            contract.clone(),
+                            *game,
+                            services.services(*game, platform)?,
+                        )?
+                        .with_client(client.clone())
+                        .with_retry_policy(retry.clone())
+                        .with_non_idempotent_retry_policy(non_idempotent_retry.clone()))
+                    };
+
+                    if dry_run {
+                        let several = dry_run_accounts.len() > 1;
+                        for (i, (label, userid, platform)) in dry_run_accounts.iter().enumerate() {
+                            let plan = contract_for(*platform).and_then(|mut contract| {
+                                Ok(serde_json::to_string_pretty(&contract.dry_run(userid)?)?)
+                            });
+                            let written = plan.and_then(|plan| match &dry_run_dir {
+                                Some(dir) => {
+                                    let stem = Path::new(&file)
+                                        .file_stem()
+                                        .map(|s| s.to_string_lossy().to_string())
+                                        .unwrap_or_else(|| file.clone());
+                                    let name = if several {
+                                        format!("{}.{:?}.{}.json", stem, game, i + 1)
+                                    } else {
+                                        format!("{}.{:?}.json", stem, game)
+                                    };
+                                    let out = Path::new(dir).join(name);
+                                    fs::write(&out, plan)?;
+                                    info!(
+                                        "Dry run of {} [{:?}] with account {} written to {}",
+                                        file,
+                                        game,
+                                        label,
+                                        out.display()
+                                    );
+                                    Ok(())
+                                }
+                                None => {
+                                    info!("Dry run of {} [{:?}] with account {}", file, game, label);
+                                    println!("{}", plan);
+                                    Ok(())
+                                }
+                            });
+                            if let Err(e) = &written {
+                                warn!("{} [{:?}] with account {}: {:#}", file, game, label, e);
+                            }
+                            dry_runs.push(written.is_ok());
+                        }
+                        continue;
+                    }
+
+                    for (i, label) in labels.iter().enumerate() {
+                        let contract = match contract_for(platforms[i]) {
+                            Ok(contract) => contract,
+                            Err(e) => {
+                                warn!("{} [{:?}] with account {}: {:#}", file, game, label, e);
+                                let now = Utc::now();
+                                handles.push(tokio::spawn(std::future::ready(JobResult {
+                                    file: file.clone(),
+                                    game: *game,
+                                    account: label.clone(),
+                                    started_at: now,
+                                    finished_at: now,
+                                    result: Err(format!("{:#}", e)),
+                                })));
+                                continue;
+                            }
+                        };
+                        let job = publish_job(
+                            contract,
+                            file.clone(),
+                            *game,
+                            shared_accounts[i].clone(),
+                            credentials.clone(),
+                            all_jobs.clone(),
+                        );
+                        let tag = format!("{} {:?} {}", file, game, label);
+                        handles.push(tokio::spawn(logging::JOB.scope(tag, job)));
                     }
                 }
+            }
+            if dry_run {
+                let failed = dry_runs.iter().filter(|ok| !**ok).count();
+                let outcome = BatchOutcome::from_counts(dry_runs.len(), failed);
+                info!("Dry run finished: {:?}", outcome);
+                std::process::exit(outcome.exit_code());
+            }
 
-                if hitman3 {
-                    let mut contract =
-                        Contract::from_contract_json(contract.clone(), PublishTypes::HITMAN3)?;
-                    for bearer in &bearer {
-                        contractids.push((file.clone(),PublishTypes::HITMAN3,contract.publish_contract(&userid, &bearer).await?));
+            let mut results = vec![];
+            for handle in handles {
+                results.push(handle.await?);
+            }
+
+            let outcome = BatchOutcome::of(&results);
+            info!("Publish finished: {:?}", outcome);
+            print!("{}", report(&results, output)?);
+            std::process::exit(outcome.exit_code());
+        }
+        Commands::Validate {
+            file,
+            hitman1,
+            hitman2,
+            hitman3,
+        } => {
+            // Without a game flag a file only has to suit one of the games, as most
+            // contracts are made for a single one.
+            let mut games = selected_games(hitman1, hitman2, hitman3);
+            let any_game = games.is_empty();
+            if any_game {
+                games = PublishTypes::ALL.to_vec();
+            }
+            let mut failed = 0;
+            for file in file {
+                let contract = match fs::read_to_string(&file)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|contract| ContractFile::from_json_str(contract.as_str()))
+                {
+                    Ok(contract) => contract,
+                    Err(e) => {
+                        warn!("{}: {}", file, e);
+                        failed += 1;
+                        continue;
                     }
+                };
+                let results = games
+                    .iter()
+                    .map(|game| (*game, validate(&contract, *game)))
+                    .collect::<Vec<_>>();
+                let accepted = results.iter().any(|(_, problems)| problems.is_empty());
+                for (game, problems) in results {
+                    if problems.is_empty() {
+                        info!("{} [{:?}]: OK", file, game);
+                    } else if any_game && accepted {
+                        for problem in problems {
+                            info!("{} [{:?}]: not for this game: {}", file, game, problem);
+                        }
+                    } else {
+                        for problem in problems {
+                            warn!("{} [{:?}]: {}", file, game, problem);
+                        }
+                        if !any_game {
+                            failed += 1;
+                        }
+                    }
+                }
+                if any_game && !accepted {
+                    failed += 1;
                 }
             }
-            info!("Publish succeeded. Result:{:#?}",&contractids);
+            if failed > 0 {
+                return Err(format!("{} check(s) failed", failed).into());
+            }
+        }
+        Commands::ProbeVersion {
+            accounts,
+            hitman1,
+            hitman2,
+            hitman3,
+            try_versions,
+            services,
+            client,
+        } => {
+            let mut games = selected_games(hitman1, hitman2, hitman3);
+            if games.is_empty() {
+                games = PublishTypes::ALL.to_vec();
+            }
+            let services = services.load()?;
+            let client = client.build()?;
+            let account = match accounts.load(true)?.accounts.into_iter().next() {
+                Some(account) => account,
+                None => return Err("Give a bearer to probe with".into()),
+            };
+            let mut cache = VersionCache::load_default()?;
+            let mut failed = 0;
+            let platform = services.platform_of(&account);
+            for game in games {
+                let services = match services.services(game, platform) {
+                    Ok(services) => services,
+                    Err(e) => {
+                        warn!("{:?}: {:#}", game, e);
+                        failed += 1;
+                        continue;
+                    }
+                };
+                let mut candidates = try_versions.clone();
+                candidates.extend(services.headers.version.clone());
+                candidates.extend(services.headers.fallback_versions.clone());
+                candidates.extend(HeaderProfile::official(game).version);
+                let mut seen = std::collections::HashSet::new();
+                candidates.retain(|version| seen.insert(version.clone()));
+                if candidates.is_empty() {
+                    warn!("{:?}: no version to try, pass one with --try", game);
+                    failed += 1;
+                    continue;
+                }
+
+                match probe_version(&client, &services, game, &account.bearer, &candidates).await {
+                    Ok(version) => {
+                        info!("{:?}: using version {}", game, version);
+                        cache.set(game, &version);
+                    }
+                    Err(e) => {
+                        warn!("{:?}: {:#}", game, e);
+                        failed += 1;
+                    }
+                }
+            }
+            cache.save()?;
+            info!("Saved versions to {}", cache.path().display());
+            if failed > 0 {
+                return Err(format!("{} probe(s) failed", failed).into());
+            }
+        }
+        Commands::Auth {
+            command:
+                AuthCommands::Check {
+                    accounts,
+                    online,
+                    hitman1,
+                    hitman2,
+                    hitman3,
+                    services,
+                    client,
+                },
+        } => {
+            let services = services.load()?;
+            let client = client.build()?;
+            let mut failed = 0;
+            for account in accounts.load(true)?.accounts {
+                // Before anything about the account is logged.
+                account.register_secrets();
+                let label = account.label();
+                let claims = match account.claims() {
+                    Ok(claims) => claims,
+                    Err(e) => {
+                        warn!("{}: {:#}", label, e);
+                        failed += 1;
+                        continue;
+                    }
+                };
+                info!(
+                    "{}: user ID {}, issuer {}, platform {}, audience {}, game {}, expires {}",
+                    label,
+                    claims.user_id().unwrap_or("unknown"),
+                    claims.iss.as_deref().unwrap_or("unknown"),
+                    claims.platform().unwrap_or("unknown"),
+                    claims.audiences().join(", "),
+                    claims.game().map_or("unknown".to_string(), |g| format!("{:?}", g)),
+                    claims
+                        .expires_at()
+                        .map_or("never".to_string(), |exp| exp.to_rfc3339()),
+                );
+
+                let mut games = selected_games(hitman1, hitman2, hitman3);
+                if games.is_empty() {
+                    games = match claims.game() {
+                        Some(game) => vec![game],
+                        None => PublishTypes::ALL.to_vec(),
+                    };
+                }
+                for game in games {
+                    let check = match claims.check_for(game) {
+                        Ok(()) if online => match account.user_id() {
+                            Ok(userid) => {
+                                match services.services(game, services.platform_of(&account)) {
+                                    Ok(services) => {
+                                        check_online(&client, &services, &userid, &account.bearer).await
+                                    }
+                                    Err(e) => Err(e),
+                                }
+                            }
+                            Err(e) => Err(e),
+                        },
+                        check => check,
+                    };
+                    match check {
+                        Ok(()) => info!("{} [{:?}]: OK", label, game),
+                        Err(e) => {
+                            warn!("{} [{:?}]: {:#}", label, game, e);
+                            failed += 1;
+                        }
+                    }
+                }
+            }
+            if failed > 0 {
+                return Err(format!("{} check(s) failed", failed).into());
+            }
+        }
+        Commands::Auth {
+            command:
+                AuthCommands::SaveProfile {
+                    name,
+                    accounts,
+                    user_id,
+                    refresh_token,
+                },
+        } => {
+            let mut account = match accounts.load(true)?.accounts.as_slice() {
+                [account] => account.clone(),
+                _ => return Err("Give exactly one bearer to save as a profile".into()),
+            };
+            if user_id.is_some() {
+                account.user_id = user_id;
+            }
+            if refresh_token.is_some() {
+                account.refresh_token = refresh_token;
+            }
+            let mut profiles = Profiles::load_default()?;
+            profiles.set(&name, account);
+            profiles.save()?;
+            info!("Saved profile {} to {}", name, profiles.path().display());
         }
     }
 
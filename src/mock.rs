@@ -0,0 +1,378 @@
+//! A local stand-in for the Hitman online service.
+//!
+//! Serves the endpoints of [`Services`](crate::Services) on 127.0.0.1,
+//! records every request it gets and can be told to fail on purpose.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use log::info;
+use serde_json::{json, Value};
+use tokio::sync::oneshot;
+
+pub use crate::services::Endpoint;
+use crate::services::{
+    CONTRACTCREATEPAGE_PATH, CREATEFROMPARAMS_PATH, GETFORPLAY2_PATH, GETPROFILE_PATH,
+    OAUTHTOKEN_PATH, SAVEEVENTS2_PATH,
+};
+
+fn endpoint_from_path(path: &str) -> Option<Endpoint> {
+    match path {
+        GETFORPLAY2_PATH => Some(Endpoint::GetForPlay2),
+        SAVEEVENTS2_PATH => Some(Endpoint::SaveEvents2),
+        CONTRACTCREATEPAGE_PATH => Some(Endpoint::ContractCreatePage),
+        CREATEFROMPARAMS_PATH => Some(Endpoint::CreateFromParams),
+        GETPROFILE_PATH => Some(Endpoint::GetProfile),
+        OAUTHTOKEN_PATH => Some(Endpoint::OAuthToken),
+        _ => None,
+    }
+}
+
+/// A way for the mock to misbehave.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// 401 Unauthorized.
+    Unauthorized,
+    /// 500 Internal Server Error.
+    ServerError,
+    /// 429 Too Many Requests with `Retry-After: 1`.
+    RateLimited,
+    /// 200 OK with a body that isn't JSON.
+    MalformedJson,
+    /// A GetForPlay2 response without `ContractSessionId`.
+    MissingContractSessionId,
+    /// 400 Bad Request turning down the `Version` header, as after a game update.
+    VersionMismatch,
+}
+
+impl FromStr for Failure {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "401" | "unauthorized" => Ok(Failure::Unauthorized),
+            "500" | "servererror" => Ok(Failure::ServerError),
+            "429" | "ratelimited" => Ok(Failure::RateLimited),
+            "malformedjson" => Ok(Failure::MalformedJson),
+            "missingcontractsessionid" => Ok(Failure::MissingContractSessionId),
+            "versionmismatch" => Ok(Failure::VersionMismatch),
+            _ => Err(anyhow!("Unknown failure: {}", s)),
+        }
+    }
+}
+
+/// A request the mock received.
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn json(&self) -> Result<Value> {
+        Ok(serde_json::from_str(&self.body)?)
+    }
+}
+
+struct Injection {
+    failure: Failure,
+    remaining: Option<usize>,
+}
+
+#[derive(Default)]
+struct MockState {
+    requests: Vec<RecordedRequest>,
+    failures: HashMap<Endpoint, Injection>,
+    /// Versions the mock accepts in the `Version` header; empty accepts any.
+    versions: Vec<String>,
+}
+
+/// A running mock service. It shuts down when dropped.
+pub struct MockService {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    _shutdown: oneshot::Sender<()>,
+}
+
+impl MockService {
+    /// Starts the mock on a free port of 127.0.0.1.
+    pub async fn start() -> Result<Self> {
+        Self::start_on(([127, 0, 0, 1], 0).into()).await
+    }
+
+    pub async fn start_on(addr: SocketAddr) -> Result<Self> {
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let service_state = state.clone();
+        let make_svc = make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| handle(state.clone(), req)))
+            }
+        });
+        let server = Server::try_bind(&addr)?.serve(make_svc);
+        let addr = server.local_addr();
+        let (tx, rx) = oneshot::channel::<()>();
+        tokio::spawn(server.with_graceful_shutdown(async {
+            rx.await.ok();
+        }));
+        Ok(MockService {
+            addr,
+            state,
+            _shutdown: tx,
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The base url to put in front of the service paths, e.g. `http://127.0.0.1:1234`.
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Makes every following request to `endpoint` fail.
+    pub fn fail(&self, endpoint: Endpoint, failure: Failure) {
+        self.inject(endpoint, failure, None)
+    }
+
+    /// Makes the next `times` requests to `endpoint` fail.
+    pub fn fail_times(&self, endpoint: Endpoint, failure: Failure, times: usize) {
+        self.inject(endpoint, failure, Some(times))
+    }
+
+    /// Answers requests whose `Version` header isn't one of `versions` with 400 Bad Request,
+    /// like the service does after a game update.
+    pub fn accept_versions(&self, versions: &[&str]) {
+        self.state.lock().unwrap().versions = versions.iter().map(|v| v.to_string()).collect();
+    }
+
+    pub fn clear_failures(&self) {
+        self.state.lock().unwrap().failures.clear();
+    }
+
+    fn inject(&self, endpoint: Endpoint, failure: Failure, remaining: Option<usize>) {
+        self.state
+            .lock()
+            .unwrap()
+            .failures
+            .insert(endpoint, Injection { failure, remaining });
+    }
+
+    /// Every request received so far, oldest first.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    pub fn requests_to(&self, endpoint: Endpoint) -> Vec<RecordedRequest> {
+        self.requests()
+            .into_iter()
+            .filter(|r| endpoint_from_path(&r.path) == Some(endpoint))
+            .collect()
+    }
+}
+
+async fn handle(
+    state: Arc<Mutex<MockState>>,
+    req: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let method = req.method().to_string();
+    let path = req.uri().path().to_string();
+    let headers = req
+        .headers()
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
+        .collect::<Vec<_>>();
+    let body = hyper::body::to_bytes(req.into_body())
+        .await
+        .map(|b| String::from_utf8_lossy(&b).to_string())
+        .unwrap_or_default();
+    info!("Mock received {} {} Body:{}", method, path, body);
+
+    let request = RecordedRequest {
+        method,
+        path,
+        headers,
+        body,
+    };
+    let endpoint = endpoint_from_path(&request.path);
+    let (failure, version_accepted) = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(request.clone());
+        let version_accepted = state.versions.is_empty()
+            || request
+                .header("version")
+                .is_some_and(|v| state.versions.iter().any(|accepted| accepted == v));
+        (
+            endpoint.and_then(|endpoint| take_failure(&mut state, endpoint)),
+            version_accepted,
+        )
+    };
+
+    let response = match (endpoint, failure) {
+        (None, _) => reply(StatusCode::NOT_FOUND, "Not Found".to_string()),
+        (Some(endpoint), _) if endpoint != Endpoint::OAuthToken && !version_accepted => {
+            version_rejected(&request)
+        }
+        (_, Some(Failure::VersionMismatch)) => version_rejected(&request),
+        (_, Some(Failure::Unauthorized)) => reply(StatusCode::UNAUTHORIZED, String::new()),
+        (_, Some(Failure::ServerError)) => reply(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Internal Server Error".to_string(),
+        ),
+        (_, Some(Failure::RateLimited)) => {
+            let mut response = reply(StatusCode::TOO_MANY_REQUESTS, String::new());
+            response
+                .headers_mut()
+                .insert("Retry-After", "1".parse().unwrap());
+            response
+        }
+        (_, Some(Failure::MalformedJson)) => reply(StatusCode::OK, "{\"Contract\":".to_string()),
+        (Some(endpoint), _)
+            if endpoint != Endpoint::OAuthToken && request.header("authorization").is_none() =>
+        {
+            reply(StatusCode::UNAUTHORIZED, String::new())
+        }
+        (Some(endpoint), failure) => {
+            let body = respond(endpoint, &request, failure);
+            reply(StatusCode::OK, body.to_string())
+        }
+    };
+    Ok(response)
+}
+
+fn take_failure(state: &mut MockState, endpoint: Endpoint) -> Option<Failure> {
+    let injection = state.failures.get_mut(&endpoint)?;
+    let failure = injection.failure;
+    match injection.remaining {
+        Some(0) => {
+            state.failures.remove(&endpoint);
+            return None;
+        }
+        Some(ref mut n) => *n -= 1,
+        None => {}
+    }
+    Some(failure)
+}
+
+fn version_rejected(request: &RecordedRequest) -> Response<Body> {
+    reply(
+        StatusCode::BAD_REQUEST,
+        json!({
+            "message": format!(
+                "Client version {} is not supported",
+                request.header("version").unwrap_or("none")
+            )
+        })
+        .to_string(),
+    )
+}
+
+fn reply(status: StatusCode, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json; charset=utf-8")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn respond(endpoint: Endpoint, request: &RecordedRequest, failure: Option<Failure>) -> Value {
+    let body = request.json().unwrap_or(Value::Null);
+    match endpoint {
+        Endpoint::GetForPlay2 => {
+            let contract_id = body["id"].as_str().unwrap_or("").to_string();
+            let mut response = json!({
+                "Contract": {
+                    "Metadata": {
+                        "Id": contract_id,
+                        "Type": "mission",
+                    },
+                    "Data": {
+                        "Objectives": [],
+                        "GameChangers": [],
+                        "Bricks": [],
+                    }
+                },
+                "ContractSessionId": format!(
+                    "{}-{}",
+                    fastrand::u64(1_000_000_000_000_000..u64::MAX),
+                    contract_id
+                ),
+                "ContractProgressionData": null,
+            });
+            if failure == Some(Failure::MissingContractSessionId) {
+                response.as_object_mut().unwrap().remove("ContractSessionId");
+            }
+            response
+        }
+        Endpoint::SaveEvents2 => json!({
+            "LastEventTicks": chrono::Utc::now().timestamp(),
+            "PushMessages": [],
+        }),
+        Endpoint::ContractCreatePage => json!({
+            "template": null,
+            "data": {
+                "Contract": {
+                    "ContractId": uuid::Uuid::new_v4().to_string(),
+                    "ContractPublicId": format!(
+                        "1-{:02}-{:07}-{:02}",
+                        fastrand::u8(0..100),
+                        fastrand::u32(0..10_000_000),
+                        fastrand::u8(0..100)
+                    ),
+                }
+            }
+        }),
+        Endpoint::CreateFromParams => {
+            let data = &body["creationData"];
+            json!({
+                "Metadata": {
+                    "Id": data["ContractId"],
+                    "PublicId": data["ContractPublicId"],
+                    "Title": data["Title"],
+                    "Description": data["Description"],
+                    "Type": "usercreated",
+                },
+                "Data": {
+                    "Objectives": [],
+                }
+            })
+        }
+        Endpoint::OAuthToken => json!({
+            "access_token": format!("mock-access-{}", uuid::Uuid::new_v4()),
+            "refresh_token": format!("mock-refresh-{}", uuid::Uuid::new_v4()),
+            "token_type": "bearer",
+            "expires_in": 3600,
+        }),
+        Endpoint::GetProfile => json!({
+            "Id": body["id"],
+            "LinkedAccounts": {},
+            "Extensions": {},
+            "ETag": null,
+            "Gamertag": "mock",
+            "DevId": null,
+            "SteamId": null,
+            "StadiaId": null,
+            "EpicId": null,
+            "NintendoId": null,
+            "XboxLiveId": null,
+            "PSNAccountId": null,
+            "PSNOnlineId": null,
+        }),
+    }
+}
@@ -0,0 +1,152 @@
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+
+/// How often and how patiently a failed request is sent again.
+///
+/// Requests are retried on 429, 5xx and connection errors. A `Retry-After`
+/// header from the service takes precedence over the computed backoff, up to `max_backoff`.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Backoff before the second attempt, doubled for each attempt after that.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Random spread applied to each backoff, from 0.0 (none) to 1.0 (±100%).
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Sends every request exactly once.
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    pub fn should_retry(&self, attempt: u32, status: StatusCode) -> bool {
+        attempt < self.max_attempts
+            && (status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error())
+    }
+
+    /// The time to wait after failed attempt number `attempt` (starting at 1).
+    ///
+    /// A `Retry-After` longer than `max_backoff` is cut short, so a service asking for a
+    /// day's pause can't stall the whole batch.
+    pub fn backoff(&self, attempt: u32, headers: Option<&HeaderMap>) -> Duration {
+        if let Some(retry_after) = headers.and_then(retry_after) {
+            return retry_after.min(self.max_backoff);
+        }
+        let exponential = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff);
+        let jitter = self.jitter.clamp(0.0, 1.0) * (fastrand::f64() * 2.0 - 1.0);
+        exponential.mul_f64(1.0 + jitter)
+    }
+}
+
+/// Reads `Retry-After` as either delay seconds or an HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+        .to_std()
+        .ok()
+        .or(Some(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(jitter: f64) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+            jitter,
+        }
+    }
+
+    fn retry_after_header(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn doubles_backoff_up_to_max() {
+        let policy = policy(0.0);
+        let backoffs = (1..=6)
+            .map(|attempt| policy.backoff(attempt, None).as_millis())
+            .collect::<Vec<_>>();
+        assert_eq!(backoffs, [100, 200, 400, 800, 1000, 1000]);
+        assert_eq!(policy.backoff(u32::MAX, None), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn keeps_jitter_within_bounds() {
+        let policy = policy(0.5);
+        for _ in 0..1000 {
+            let backoff = policy.backoff(2, None);
+            assert!(backoff >= Duration::from_millis(100), "{:?}", backoff);
+            assert!(backoff <= Duration::from_millis(300), "{:?}", backoff);
+        }
+        // Anything above 1.0 is treated as 1.0, so a backoff never turns negative.
+        let policy = RetryPolicy {
+            jitter: 5.0,
+            ..policy
+        };
+        for _ in 0..1000 {
+            assert!(policy.backoff(1, None) <= Duration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn reads_retry_after_seconds() {
+        let headers = retry_after_header("7");
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(7)));
+        let policy = RetryPolicy {
+            max_backoff: Duration::from_secs(30),
+            ..policy(0.5)
+        };
+        assert_eq!(policy.backoff(1, Some(&headers)), Duration::from_secs(7));
+    }
+
+    #[test]
+    fn reads_retry_after_http_date() {
+        let date = chrono::Utc::now() + chrono::Duration::seconds(20);
+        let headers = retry_after_header(&date.format("%a, %d %b %Y %H:%M:%S GMT").to_string());
+        let wait = retry_after(&headers).unwrap();
+        assert!(wait > Duration::from_secs(17) && wait <= Duration::from_secs(20), "{:?}", wait);
+
+        let past = retry_after_header("Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(retry_after(&past), Some(Duration::ZERO));
+        assert_eq!(retry_after(&retry_after_header("soon")), None);
+    }
+
+    #[test]
+    fn caps_retry_after_at_max_backoff() {
+        let policy = policy(0.0);
+        let headers = retry_after_header("86400");
+        assert_eq!(policy.backoff(1, Some(&headers)), Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,468 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::PublishTypes;
+
+pub const GETFORPLAY2_PATH: &str = "/authentication/api/userchannel/ContractsService/GetForPlay2";
+pub const SAVEEVENTS2_PATH: &str = "/authentication/api/userchannel/EventsService/SaveEvents2";
+pub const CONTRACTCREATEPAGE_PATH: &str = "/profiles/page/contractcreation/create";
+pub const CREATEFROMPARAMS_PATH: &str =
+    "/authentication/api/userchannel/ContractsService/CreateFromParams";
+pub const GETPROFILE_PATH: &str = "/authentication/api/userchannel/ProfileService/GetProfile";
+pub const OAUTHTOKEN_PATH: &str = "/oauth/token";
+
+pub const HITMAN1_BASE_URL: &str = "https://pc-service.hitman.io";
+pub const HITMAN2_BASE_URL: &str = "https://pc2-service.hitman.io";
+pub const HITMAN3_BASE_URL: &str = "https://hm3-service.hitman.io";
+
+// Copied from requests of the HITMAN2 and HITMAN3 game clients.
+pub const HITMAN2_VERSION: &str = "7.17.0";
+pub const HITMAN3_VERSION: &str = "8.7.0";
+
+/// The store an account belongs to. Each has its own service hosts and token audiences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    Steam,
+    Epic,
+    Xbox,
+    PlayStation,
+}
+
+impl Platform {
+    /// Reads the `platform` (or `auth:method`) claim of a bearer, e.g. `steam` or `epic`.
+    pub fn from_claim(claim: &str) -> Option<Self> {
+        let claim = claim.to_lowercase();
+        if claim.contains("steam") {
+            Some(Platform::Steam)
+        } else if claim.contains("epic") {
+            Some(Platform::Epic)
+        } else if claim.contains("xbox") {
+            Some(Platform::Xbox)
+        } else if ["psn", "playstation", "ps4", "ps5"]
+            .iter()
+            .any(|name| claim.contains(name))
+        {
+            Some(Platform::PlayStation)
+        } else {
+            None
+        }
+    }
+
+    /// The official host of `game` on this platform, if there is one built in.
+    ///
+    /// The PC stores share the PC hosts. Console hosts aren't built in and have to be set
+    /// under `platforms` in a services config.
+    pub fn base_url(self, game: PublishTypes) -> Option<&'static str> {
+        match (self, game) {
+            (Platform::Steam | Platform::Epic, _) => Some(official_base_url(game)),
+            (Platform::Xbox | Platform::PlayStation, _) => None,
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Platform::Steam => "steam",
+            Platform::Epic => "epic",
+            Platform::Xbox => "xbox",
+            Platform::PlayStation => "playstation",
+        })
+    }
+}
+
+impl FromStr for Platform {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "steam" => Ok(Platform::Steam),
+            "epic" => Ok(Platform::Epic),
+            "xbox" => Ok(Platform::Xbox),
+            "playstation" | "psn" => Ok(Platform::PlayStation),
+            _ => Err(anyhow!("Unknown platform: {}", s)),
+        }
+    }
+}
+
+/// One of the endpoints in [`Services`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    GetForPlay2,
+    SaveEvents2,
+    ContractCreatePage,
+    CreateFromParams,
+    GetProfile,
+    OAuthToken,
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Endpoint::GetForPlay2 => "GetForPlay2",
+            Endpoint::SaveEvents2 => "SaveEvents2",
+            Endpoint::ContractCreatePage => "ContractCreatePage",
+            Endpoint::CreateFromParams => "CreateFromParams",
+            Endpoint::GetProfile => "GetProfile",
+            Endpoint::OAuthToken => "OAuthToken",
+        })
+    }
+}
+
+impl FromStr for Endpoint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "getforplay2" => Ok(Endpoint::GetForPlay2),
+            "saveevents2" => Ok(Endpoint::SaveEvents2),
+            "contractcreatepage" => Ok(Endpoint::ContractCreatePage),
+            "createfromparams" => Ok(Endpoint::CreateFromParams),
+            "getprofile" => Ok(Endpoint::GetProfile),
+            "oauthtoken" => Ok(Endpoint::OAuthToken),
+            _ => Err(anyhow!("Unknown endpoint: {}", s)),
+        }
+    }
+}
+
+/// Headers the game client of a title sends with every request.
+///
+/// Headers left out are taken from [`HeaderProfile::official`] once the title is known,
+/// so a services config only needs the ones that changed with a game patch.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeaderProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accept: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Versions to try in order when the service rejects `version`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_versions: Vec<String>,
+}
+
+impl HeaderProfile {
+    /// What the PC client of `game` sends. There is no known `Version` of HITMAN (2016),
+    /// it has to be configured.
+    pub fn official(game: PublishTypes) -> Self {
+        let (version, user_agent) = match game {
+            PublishTypes::HITMAN1 => (
+                None,
+                "G2 Http/1.0 (Windows NT 10.0; DX11/1; d3d11/1)",
+            ),
+            PublishTypes::HITMAN2 => (
+                Some(HITMAN2_VERSION),
+                "G2 Http/1.0 (Windows NT 10.0; DX11/1; d3d11/1)",
+            ),
+            PublishTypes::HITMAN3 => (
+                Some(HITMAN3_VERSION),
+                "G2 Http/1.0 (Windows NT 10.0; DX12/1; d3d12/1)",
+            ),
+        };
+        HeaderProfile {
+            version: version.map(str::to_string),
+            user_agent: Some(user_agent.to_string()),
+            accept: Some("application/json".to_string()),
+            content_type: Some("application/json; charset=utf-8".to_string()),
+            fallback_versions: vec![],
+        }
+    }
+
+    /// This profile with the headers it leaves out taken from `fallback`.
+    pub fn or(self, fallback: HeaderProfile) -> Self {
+        HeaderProfile {
+            version: self.version.or(fallback.version),
+            user_agent: self.user_agent.or(fallback.user_agent),
+            accept: self.accept.or(fallback.accept),
+            content_type: self.content_type.or(fallback.content_type),
+            fallback_versions: if self.fallback_versions.is_empty() {
+                fallback.fallback_versions
+            } else {
+                self.fallback_versions
+            },
+        }
+    }
+
+    /// The headers as name and value pairs, in the order they are sent.
+    pub fn headers(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("Version", &self.version),
+            ("User-Agent", &self.user_agent),
+            ("Accept", &self.accept),
+            ("Content-Type", &self.content_type),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value.as_deref()?)))
+        .collect()
+    }
+
+    pub fn apply(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        for (name, value) in self.headers() {
+            request = request.header(name, value);
+        }
+        request
+    }
+}
+
+/// Endpoints of a Hitman online service.
+///
+/// Each endpoint is either a path below `base_url` or a full url of its own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Services {
+    /// Left out in a services config, the official service of the game.
+    #[serde(default)]
+    pub base_url: String,
+    #[serde(default = "default_getforplay2")]
+    pub getforplay2: String,
+    #[serde(default = "default_saveevents2")]
+    pub saveevents2: String,
+    #[serde(default = "default_contractcreatepage")]
+    pub contractcreatepage: String,
+    #[serde(default = "default_createfromparams")]
+    pub createfromparams: String,
+    /// Used by `auth check --online` to test a bearer.
+    #[serde(default = "default_getprofile")]
+    pub getprofile: String,
+    /// Mints new bearers from refresh tokens.
+    #[serde(default = "default_oauthtoken")]
+    pub oauthtoken: String,
+    /// Headers sent with every request.
+    #[serde(default)]
+    pub headers: HeaderProfile,
+}
+
+fn official_base_url(game: PublishTypes) -> &'static str {
+    match game {
+        PublishTypes::HITMAN1 => HITMAN1_BASE_URL,
+        PublishTypes::HITMAN2 => HITMAN2_BASE_URL,
+        PublishTypes::HITMAN3 => HITMAN3_BASE_URL,
+    }
+}
+
+fn default_getforplay2() -> String {
+    GETFORPLAY2_PATH.to_string()
+}
+fn default_saveevents2() -> String {
+    SAVEEVENTS2_PATH.to_string()
+}
+fn default_contractcreatepage() -> String {
+    CONTRACTCREATEPAGE_PATH.to_string()
+}
+fn default_createfromparams() -> String {
+    CREATEFROMPARAMS_PATH.to_string()
+}
+fn default_getprofile() -> String {
+    GETPROFILE_PATH.to_string()
+}
+fn default_oauthtoken() -> String {
+    OAUTHTOKEN_PATH.to_string()
+}
+
+impl Services {
+    /// A service with the standard endpoint paths below `base_url`.
+    pub fn from_base_url(base_url: &str) -> Self {
+        Services {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            getforplay2: default_getforplay2(),
+            saveevents2: default_saveevents2(),
+            contractcreatepage: default_contractcreatepage(),
+            createfromparams: default_createfromparams(),
+            getprofile: default_getprofile(),
+            oauthtoken: default_oauthtoken(),
+            headers: HeaderProfile::default(),
+        }
+    }
+
+    /// The official service of `game`.
+    pub fn official(game: PublishTypes) -> Self {
+        Self::from_base_url(official_base_url(game)).for_game(game)
+    }
+
+    /// Fills in what was left out with the official values of `game`: the base url and
+    /// the headers of its game client.
+    pub fn for_game(mut self, game: PublishTypes) -> Self {
+        if self.base_url.is_empty() {
+            self.base_url = official_base_url(game).to_string();
+        }
+        self.headers = self.headers.or(HeaderProfile::official(game));
+        self
+    }
+
+    pub fn url(&self, endpoint: &str) -> String {
+        if endpoint.starts_with("http://") || endpoint.starts_with("https://") {
+            endpoint.to_string()
+        } else {
+            format!("{}{}", self.base_url.trim_end_matches('/'), endpoint)
+        }
+    }
+
+    pub fn endpoint_url(&self, endpoint: Endpoint) -> String {
+        match endpoint {
+            Endpoint::GetForPlay2 => self.getforplay2_url(),
+            Endpoint::SaveEvents2 => self.saveevents2_url(),
+            Endpoint::ContractCreatePage => self.contractcreatepage_url(),
+            Endpoint::CreateFromParams => self.createfromparams_url(),
+            Endpoint::GetProfile => self.getprofile_url(),
+            Endpoint::OAuthToken => self.oauthtoken_url(),
+        }
+    }
+
+    pub fn getforplay2_url(&self) -> String {
+        self.url(&self.getforplay2)
+    }
+    pub fn saveevents2_url(&self) -> String {
+        self.url(&self.saveevents2)
+    }
+    pub fn contractcreatepage_url(&self) -> String {
+        self.url(&self.contractcreatepage)
+    }
+    pub fn createfromparams_url(&self) -> String {
+        self.url(&self.createfromparams)
+    }
+    pub fn getprofile_url(&self) -> String {
+        self.url(&self.getprofile)
+    }
+    pub fn oauthtoken_url(&self) -> String {
+        self.url(&self.oauthtoken)
+    }
+}
+
+/// Service endpoints per game, as read from a services config file:
+///
+/// ```json
+/// {
+///     "HITMAN2": { "base_url": "http://127.0.0.1:8080" },
+///     "HITMAN3": { "base_url": "http://127.0.0.1:8080", "saveevents2": "/events", "headers": { "version": "8.8.0" } },
+///     "platforms": {
+///         "xbox": { "HITMAN3": { "base_url": "https://xbox.example.com" } }
+///     }
+/// }
+/// ```
+///
+/// Games left out use their official service.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ServicesConfig {
+    #[serde(rename = "HITMAN1", default, skip_serializing_if = "Option::is_none")]
+    pub hitman1: Option<Services>,
+    #[serde(rename = "HITMAN2", default, skip_serializing_if = "Option::is_none")]
+    pub hitman2: Option<Services>,
+    #[serde(rename = "HITMAN3", default, skip_serializing_if = "Option::is_none")]
+    pub hitman3: Option<Services>,
+    /// Services of accounts of one platform, see [`ServicesConfig::services_on`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub platforms: BTreeMap<Platform, ServicesConfig>,
+}
+
+impl ServicesConfig {
+    pub fn from_file(path: &str) -> Result<Self> {
+        let config = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read services config {}", path))?;
+        serde_json::from_str(&config).with_context(|| format!("Invalid services config {}", path))
+    }
+
+    /// The services configured for `game`, added with only the defaults if there are none.
+    pub fn entry_mut(&mut self, game: PublishTypes) -> &mut Services {
+        let services = match game {
+            PublishTypes::HITMAN1 => &mut self.hitman1,
+            PublishTypes::HITMAN2 => &mut self.hitman2,
+            PublishTypes::HITMAN3 => &mut self.hitman3,
+        };
+        services.get_or_insert_with(|| Services::from_base_url(""))
+    }
+
+    fn get(&self, game: PublishTypes) -> Option<&Services> {
+        match game {
+            PublishTypes::HITMAN1 => self.hitman1.as_ref(),
+            PublishTypes::HITMAN2 => self.hitman2.as_ref(),
+            PublishTypes::HITMAN3 => self.hitman3.as_ref(),
+        }
+    }
+
+    pub fn services(&self, game: PublishTypes) -> Services {
+        match self.get(game) {
+            Some(services) => services.clone().for_game(game),
+            None => Services::official(game),
+        }
+    }
+
+    /// The services of `game` for accounts of `platform`, or [`ServicesConfig::services`]
+    /// when the platform isn't known.
+    ///
+    /// An entry under `platforms` wins over the top-level one and takes the headers it
+    /// leaves out from there. A `base_url` left out is the host of the platform.
+    pub fn services_on(&self, game: PublishTypes, platform: Option<Platform>) -> Result<Services> {
+        let platform = match platform {
+            Some(platform) => platform,
+            None => return Ok(self.services(game)),
+        };
+        let top = self.get(game);
+        let mut services = match self.platforms.get(&platform).and_then(|c| c.get(game)) {
+            Some(services) => {
+                let mut services = services.clone();
+                if let Some(top) = top {
+                    services.headers = services.headers.or(top.headers.clone());
+                }
+                services
+            }
+            None => top.cloned().unwrap_or_else(|| Services::from_base_url("")),
+        };
+        if services.base_url.is_empty() {
+            services.base_url = platform
+                .base_url(game)
+                .with_context(|| {
+                    format!(
+                        "No {:?} service known for {}, set platforms.{}.{:?}.base_url in --services",
+                        game, platform, platform, game
+                    )
+                })?
+                .to_string();
+        }
+        Ok(services.for_game(game))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn puts_pc_stores_on_pc_hosts() {
+        for platform in [Platform::Steam, Platform::Epic] {
+            assert_eq!(platform.base_url(PublishTypes::HITMAN1), Some(HITMAN1_BASE_URL));
+            assert_eq!(platform.base_url(PublishTypes::HITMAN2), Some(HITMAN2_BASE_URL));
+            assert_eq!(platform.base_url(PublishTypes::HITMAN3), Some(HITMAN3_BASE_URL));
+        }
+    }
+
+    #[test]
+    fn publishes_epic_accounts_to_every_game() {
+        let config = ServicesConfig::default();
+        for game in PublishTypes::ALL {
+            let services = config.services_on(game, Some(Platform::Epic)).unwrap();
+            assert_eq!(services.base_url, official_base_url(game));
+        }
+    }
+
+    #[test]
+    fn needs_configured_console_hosts() {
+        let config = ServicesConfig::default();
+        let error = config
+            .services_on(PublishTypes::HITMAN3, Some(Platform::Xbox))
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("platforms.xbox.HITMAN3.base_url"),
+            "{}",
+            error
+        );
+    }
+}
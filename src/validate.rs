@@ -0,0 +1,287 @@
+use std::collections::HashSet;
+
+use crate::contract_file::ContractFile;
+use crate::PublishTypes;
+
+static HITMAN1_LOCATIONS: &[&str] = &[
+    "ICA_FACILITY",
+    "PARIS",
+    "COASTALTOWN",
+    "MARRAKECH",
+    "BANGKOK",
+    "COLORADO",
+    "HOKKAIDO",
+];
+
+static HITMAN2_LOCATIONS: &[&str] = &[
+    "ICA_FACILITY",
+    "PARIS",
+    "COASTALTOWN",
+    "MARRAKECH",
+    "BANGKOK",
+    "COLORADO",
+    "HOKKAIDO",
+    "NEWZEALAND",
+    "MIAMI",
+    "COLOMBIA",
+    "MUMBAI",
+    "NORTHAMERICA",
+    "NORTHSEA",
+    "GREEDY",
+    "OPULENT",
+];
+
+static HITMAN3_LOCATIONS: &[&str] = &[
+    "ICA_FACILITY",
+    "PARIS",
+    "COASTALTOWN",
+    "MARRAKECH",
+    "BANGKOK",
+    "COLORADO",
+    "HOKKAIDO",
+    "NEWZEALAND",
+    "MIAMI",
+    "COLOMBIA",
+    "MUMBAI",
+    "NORTHAMERICA",
+    "NORTHSEA",
+    "GREEDY",
+    "OPULENT",
+    "GOLDEN",
+    "ANCESTRAL",
+    "EDGY",
+    "WET",
+    "ELEGANT",
+    "TRAPPED",
+    "ROCKY",
+];
+
+/// Locations a contract can be created on in `game`, without the `LOCATION_PARENT_` prefix.
+pub fn known_locations(game: PublishTypes) -> &'static [&'static str] {
+    match game {
+        PublishTypes::HITMAN1 => HITMAN1_LOCATIONS,
+        PublishTypes::HITMAN2 => HITMAN2_LOCATIONS,
+        PublishTypes::HITMAN3 => HITMAN3_LOCATIONS,
+    }
+}
+
+/// Accepts both parent locations (`LOCATION_PARENT_MIAMI`) and their
+/// sub locations (`LOCATION_MIAMI`, `LOCATION_COASTALTOWN_NIGHT`).
+fn is_known_location(location_id: &str, game: PublishTypes) -> bool {
+    known_locations(game).iter().any(|location| {
+        location_id == format!("LOCATION_PARENT_{}", location)
+            || location_id
+                .strip_prefix(&format!("LOCATION_{}", location))
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('_'))
+    })
+}
+
+fn is_guid(id: &str) -> bool {
+    id.len() == 36 && uuid::Uuid::parse_str(id).is_ok()
+}
+
+/// Checks a contract file for `game` without touching the network.
+///
+/// Returns one message per problem found; an empty list means the file is good to publish.
+pub fn validate(contract: &ContractFile, game: PublishTypes) -> Vec<String> {
+    let mut problems = check_contents(contract, game);
+    problems.extend(check_location(contract, game));
+    problems
+}
+
+/// The problem with `MissionName`, if it isn't a location of the official service of `game`.
+/// Private servers may have locations of their own.
+pub fn check_location(contract: &ContractFile, game: PublishTypes) -> Option<String> {
+    (!is_known_location(&contract.mission_name, game)).then(|| {
+        format!(
+            "MissionName: `{}` is not a known location for {:?}",
+            contract.mission_name, game
+        )
+    })
+}
+
+/// Every check of [`validate`] but the one of the location.
+pub fn check_contents(contract: &ContractFile, game: PublishTypes) -> Vec<String> {
+    let mut problems = vec![];
+    let data = &contract.create_from_params.creation_data;
+
+    if !is_guid(&contract.mission_id) {
+        problems.push(format!("MissionId: `{}` is not a GUID", contract.mission_id));
+    }
+    if contract.exit_id.is_empty() {
+        problems.push("ExitId: missing".to_string());
+    } else if !is_guid(&contract.exit_id) {
+        problems.push(format!("ExitId: `{}` is not a GUID", contract.exit_id));
+    }
+
+    if data.title.as_ref().and_then(Option::as_deref).unwrap_or("").trim().is_empty() {
+        problems.push("CreateFromParamsJ.creationData.Title: missing or empty".to_string());
+    }
+    if data.description.as_ref().and_then(Option::as_deref).unwrap_or("").trim().is_empty() {
+        problems.push("CreateFromParamsJ.creationData.Description: missing or empty".to_string());
+    }
+
+    if data.targets.is_empty() || data.targets.len() > 5 {
+        problems.push(format!(
+            "CreateFromParamsJ.creationData.Targets: expected 1 to 5 targets, found {}",
+            data.targets.len()
+        ));
+    }
+    let mut seen = HashSet::new();
+    for (i, target) in data.targets.iter().enumerate() {
+        let field = format!("CreateFromParamsJ.creationData.Targets[{}]", i);
+        if !is_guid(&target.repository_id) {
+            problems.push(format!(
+                "{}.RepositoryId: `{}` is not a GUID",
+                field, target.repository_id
+            ));
+        }
+        if !seen.insert(target.repository_id.to_lowercase()) {
+            problems.push(format!(
+                "{}.RepositoryId: `{}` is targeted more than once",
+                field, target.repository_id
+            ));
+        }
+        let weapon = target.weapon.iter().flatten();
+        let weapon = weapon.flat_map(|w| w.repository_id.iter().flatten()).next();
+        if let Some(id) = weapon.filter(|id| !id.is_empty() && !is_guid(id)) {
+            problems.push(format!("{}.Weapon.RepositoryId: `{}` is not a GUID", field, id));
+        }
+        let outfit = target.outfit.iter().flatten();
+        let outfit = outfit.flat_map(|o| o.repository_id.iter().flatten()).next();
+        if let Some(id) = outfit.filter(|id| !id.is_empty() && !is_guid(id)) {
+            problems.push(format!("{}.Outfit.RepositoryId: `{}` is not a GUID", field, id));
+        }
+    }
+
+    // Complications came with HITMAN2, the HITMAN (2016) service turns them down.
+    if game == PublishTypes::HITMAN1 {
+        let conditions = data.contract_condition_ids.iter().flatten().map(Vec::len).sum::<usize>()
+            + data.contract_conditions.iter().flatten().map(Vec::len).sum::<usize>();
+        if conditions > 0 {
+            problems.push(format!(
+                "CreateFromParamsJ.creationData: {} complication(s), HITMAN1 contracts can't have any",
+                conditions
+            ));
+        }
+    }
+    for (i, id) in data.contract_condition_ids.iter().flatten().flatten().enumerate() {
+        if !is_guid(id) {
+            problems.push(format!(
+                "CreateFromParamsJ.creationData.ContractConditionIds[{}]: `{}` is not a GUID",
+                i, id
+            ));
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn contract(location: &str, targets: &[&str], conditions: &[&str]) -> ContractFile {
+        let targets = targets
+            .iter()
+            .map(|id| json!({ "RepositoryId": id, "Selected": true }))
+            .collect::<Vec<_>>();
+        let json = json!({
+            "MissionId": "b2aac100-dfc7-4f85-b9cd-528114436f6c",
+            "ExitId": "2f8bd7e4-9154-4c8d-8e1e-9a5b4c5d9a3b",
+            "MissionName": location,
+            "CreateFromParamsJ": {
+                "creationData": {
+                    "Title": "Test contract",
+                    "Description": "Test briefing",
+                    "Targets": targets,
+                    "ContractConditionIds": conditions
+                }
+            }
+        });
+        ContractFile::from_json_str(&json.to_string()).unwrap()
+    }
+
+    const TARGET: &str = "ee454990-0c4b-49e5-9572-a67887325283";
+
+    #[test]
+    fn accepts_known_locations_of_each_game() {
+        for (game, location) in [
+            (PublishTypes::HITMAN1, "LOCATION_PARENT_HOKKAIDO"),
+            (PublishTypes::HITMAN1, "LOCATION_COASTALTOWN_NIGHT"),
+            (PublishTypes::HITMAN2, "LOCATION_PARENT_MIAMI"),
+            (PublishTypes::HITMAN2, "LOCATION_MIAMI"),
+            (PublishTypes::HITMAN3, "LOCATION_PARENT_ROCKY"),
+            (PublishTypes::HITMAN3, "LOCATION_ICA_FACILITY_SHIP"),
+        ] {
+            let problems = validate(&contract(location, &[TARGET], &[]), game);
+            assert!(problems.is_empty(), "{} {:?}: {:?}", location, game, problems);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_locations_of_each_game() {
+        for (game, location) in [
+            (PublishTypes::HITMAN1, "LOCATION_PARENT_MIAMI"),
+            (PublishTypes::HITMAN1, "LOCATION_NEWZEALAND"),
+            (PublishTypes::HITMAN2, "LOCATION_PARENT_GOLDEN"),
+            (PublishTypes::HITMAN2, "LOCATION_MIAMIBOGUS"),
+            (PublishTypes::HITMAN3, "LOCATION_PARENT_NOWHERE"),
+            (PublishTypes::HITMAN3, "LOCATION_PARENT_MIAMI_NIGHT"),
+            (PublishTypes::HITMAN3, "LOCATION_PARISIAN"),
+        ] {
+            let problems = validate(&contract(location, &[TARGET], &[]), game);
+            assert_eq!(problems.len(), 1, "{} {:?}: {:?}", location, game, problems);
+            assert!(problems[0].starts_with("MissionName:"), "{}", problems[0]);
+        }
+    }
+
+    #[test]
+    fn requires_targets() {
+        let problems = validate(&contract("LOCATION_MIAMI", &[], &[]), PublishTypes::HITMAN3);
+        assert_eq!(
+            problems,
+            ["CreateFromParamsJ.creationData.Targets: expected 1 to 5 targets, found 0"]
+        );
+
+        let problems = validate(
+            &contract("LOCATION_MIAMI", &[TARGET, "not-a-guid", TARGET], &[]),
+            PublishTypes::HITMAN3,
+        );
+        assert_eq!(
+            problems,
+            [
+                "CreateFromParamsJ.creationData.Targets[1].RepositoryId: `not-a-guid` is not a GUID",
+                "CreateFromParamsJ.creationData.Targets[2].RepositoryId: `ee454990-0c4b-49e5-9572-a67887325283` is targeted more than once",
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_condition_ids() {
+        let contract = contract(
+            "LOCATION_MIAMI",
+            &[TARGET],
+            &["1a596216-381e-4592-9798-26f156973942", "no_pacifications"],
+        );
+        assert_eq!(
+            validate(&contract, PublishTypes::HITMAN3),
+            ["CreateFromParamsJ.creationData.ContractConditionIds[1]: `no_pacifications` is not a GUID"]
+        );
+    }
+
+    #[test]
+    fn rejects_complications_for_hitman1() {
+        let contract = contract(
+            "LOCATION_PARIS",
+            &[TARGET],
+            &["1a596216-381e-4592-9798-26f156973942"],
+        );
+        assert!(validate(&contract, PublishTypes::HITMAN2).is_empty());
+        assert_eq!(
+            validate(&contract, PublishTypes::HITMAN1),
+            ["CreateFromParamsJ.creationData: 1 complication(s), HITMAN1 contracts can't have any"]
+        );
+    }
+}
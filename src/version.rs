@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use log::{info, warn};
+
+use crate::error::ServiceError;
+use crate::http::send;
+use crate::retry::RetryPolicy;
+use crate::services::{Endpoint, Services, ServicesConfig};
+use crate::PublishTypes;
+
+/// Game versions `probe-version` found to work, kept in
+/// `<config dir>/hitman_contract_creator/versions.json`:
+///
+/// ```json
+/// { "HITMAN2": "7.17.0", "HITMAN3": "8.8.0" }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct VersionCache {
+    path: PathBuf,
+    versions: BTreeMap<String, String>,
+}
+
+impl VersionCache {
+    pub fn default_path() -> Option<PathBuf> {
+        Some(
+            dirs::config_dir()?
+                .join("hitman_contract_creator")
+                .join("versions.json"),
+        )
+    }
+
+    /// Reads the cache at `path`; a missing file caches nothing.
+    pub fn load(path: &Path) -> Result<Self> {
+        let versions = match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .with_context(|| format!("Invalid version cache {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read version cache {}", path.display()))
+            }
+        };
+        Ok(VersionCache {
+            path: path.to_path_buf(),
+            versions,
+        })
+    }
+
+    pub fn load_default() -> Result<Self> {
+        Self::load(&Self::default_path().context("No user config directory found")?)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self, game: PublishTypes) -> Option<&str> {
+        self.versions
+            .get(&format!("{:?}", game))
+            .map(String::as_str)
+    }
+
+    pub fn set(&mut self, game: PublishTypes, version: &str) {
+        self.versions
+            .insert(format!("{:?}", game), version.to_string());
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.versions)?)
+            .with_context(|| format!("Failed to write version cache {}", self.path.display()))
+    }
+
+    /// Uses the cached versions for the games whose version `config` doesn't set itself.
+    pub fn apply(&self, config: &mut ServicesConfig) {
+        for game in PublishTypes::ALL {
+            if let Some(version) = self.get(game) {
+                config
+                    .entry_mut(game)
+                    .headers
+                    .version
+                    .get_or_insert_with(|| version.to_string());
+            }
+        }
+    }
+}
+
+/// Tries `candidates` in order on the contract create page of `services` and returns the
+/// first version the service accepts.
+///
+/// The create page only hands out a new contract ID, so probing doesn't leave anything behind.
+pub async fn probe_version(
+    client: &reqwest::Client,
+    services: &Services,
+    game: PublishTypes,
+    bearer: &str,
+    candidates: &[String],
+) -> Result<String> {
+    for version in candidates {
+        let mut headers = services.headers.clone();
+        headers.version = Some(version.clone());
+        let result = send(
+            Endpoint::ContractCreatePage,
+            Some(game),
+            headers
+                .apply(client.get(services.contractcreatepage_url()))
+                .bearer_auth(bearer),
+            &RetryPolicy::default(),
+        )
+        .await;
+        match result {
+            Ok(_) => {
+                info!("{:?} accepts version {}", game, version);
+                return Ok(version.clone());
+            }
+            Err(e) if is_version_mismatch(&e) => {
+                warn!("{:?} rejects version {}", game, version)
+            }
+            Err(e) => return Err(e.context(format!("Probing version {} failed", version))),
+        }
+    }
+    bail!(
+        "{:?} accepts none of the versions {}",
+        game,
+        candidates.join(", ")
+    )
+}
+
+pub(crate) fn is_version_mismatch(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<ServiceError>()
+        .is_some_and(ServiceError::is_version_mismatch)
+}
@@ -0,0 +1,224 @@
+//! Runs the command line tool, against the mock service where it needs one.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+use hitman_contract_creator::mock::MockService;
+use serde_json::{json, Value};
+
+const USER_ID: &str = "fe76faee-ecdc-4dd7-a6d5-c5b84054a87c";
+
+/// A fresh directory for the files of one test, also used as the user config directory.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("hitman_cli_{}_{}", name, uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write_contract(dir: &Path) -> PathBuf {
+    write_contract_on(dir, "LOCATION_PARENT_MIAMI", &[])
+}
+
+fn write_contract_on(dir: &Path, location: &str, conditions: &[&str]) -> PathBuf {
+    let path = dir.join("contract.json");
+    let contract = json!({
+        "MissionId": "b2aac100-dfc7-4f85-b9cd-528114436f6c",
+        "ExitId": "2f8bd7e4-9154-4c8d-8e1e-9a5b4c5d9a3b",
+        "MissionName": location,
+        "CreateFromParamsJ": {
+            "creationData": {
+                "Title": "Test contract",
+                "Description": "Test briefing",
+                "Targets": [
+                    { "RepositoryId": "ee454990-0c4b-49e5-9572-a67887325283", "Selected": true }
+                ],
+                "ContractConditionIds": conditions
+            }
+        }
+    });
+    fs::write(&path, contract.to_string()).unwrap();
+    path
+}
+
+async fn run(dir: &Path, args: &[&str]) -> Output {
+    tokio::process::Command::new(env!("CARGO_BIN_EXE_hitman_contract_creator"))
+        .args(args)
+        .current_dir(dir)
+        .env("XDG_CONFIG_HOME", dir)
+        .env("HOME", dir)
+        .env_remove("HITMAN_BEARER")
+        .env_remove("HITMAN_USER_ID")
+        .output()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn dry_run_prints_only_the_plan_and_sends_nothing() {
+    let mock = MockService::start().await.unwrap();
+    let dir = temp_dir("dry_run");
+    let contract = write_contract(&dir);
+
+    let output = run(
+        &dir,
+        &[
+            "publish",
+            "--file",
+            contract.to_str().unwrap(),
+            "--hitman3",
+            "--hitman3-url",
+            &mock.base_url(),
+            "--dry-run",
+            USER_ID,
+        ],
+    )
+    .await;
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // Logs go to stderr, so stdout is the plan and nothing else.
+    let plan: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let urls = plan
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|request| request["url"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(urls.len(), 4);
+    for url in urls {
+        assert!(url.starts_with(&mock.base_url()), "{}", url);
+    }
+    assert_eq!(plan[1]["body"]["userId"], USER_ID);
+    assert!(mock.requests().is_empty());
+}
+
+#[tokio::test]
+async fn validate_passes_files_one_game_accepts() {
+    let dir = temp_dir("validate");
+    // Complications are fine for HITMAN2 and HITMAN3, but not for HITMAN (2016).
+    let contract = write_contract_on(
+        &dir,
+        "LOCATION_PARENT_PARIS",
+        &["1a596216-381e-4592-9798-26f156973942"],
+    );
+    let contract = contract.to_str().unwrap();
+
+    let output = run(&dir, &["validate", "--file", contract]).await;
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = run(&dir, &["validate", "--file", contract, "--hitman3"]).await;
+    assert!(output.status.success());
+
+    let output = run(&dir, &["validate", "--file", contract, "--hitman1"]).await;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("complication"));
+
+    let unknown = write_contract_on(&dir, "LOCATION_PARENT_NOWHERE", &[]);
+    let output = run(&dir, &["validate", "--file", unknown.to_str().unwrap()]).await;
+    assert!(!output.status.success());
+}
+
+/// An unsigned JWT carrying `claims`.
+fn jwt(claims: Value) -> String {
+    let encode = |json: Value| base64::encode_config(json.to_string(), base64::URL_SAFE_NO_PAD);
+    format!("{}.{}.signature", encode(json!({ "alg": "none" })), encode(claims))
+}
+
+#[tokio::test]
+async fn dry_run_plans_each_account_on_its_store() {
+    let dir = temp_dir("dry_run_accounts");
+    let contract = write_contract(&dir);
+    let accounts = dir.join("accounts.json");
+    let xbox_user = "0e5d2b7a-1c3f-4e8a-9b6d-2f4a7c9e1b3d";
+    fs::write(
+        &accounts,
+        json!([
+            { "name": "pc", "bearer": jwt(json!({ "userid": USER_ID, "platform": "steam" })) },
+            { "name": "console", "bearer": jwt(json!({ "userid": xbox_user, "platform": "xbox" })) }
+        ])
+        .to_string(),
+    )
+    .unwrap();
+    let services = dir.join("services.json");
+    fs::write(
+        &services,
+        json!({
+            "HITMAN3": { "base_url": "http://pc.invalid" },
+            "platforms": { "xbox": { "HITMAN3": { "base_url": "http://xbox.invalid" } } }
+        })
+        .to_string(),
+    )
+    .unwrap();
+    let plans = dir.join("plans");
+    fs::create_dir_all(&plans).unwrap();
+
+    let output = run(
+        &dir,
+        &[
+            "publish",
+            "--file",
+            contract.to_str().unwrap(),
+            "--hitman3",
+            "--accounts",
+            accounts.to_str().unwrap(),
+            "--services",
+            services.to_str().unwrap(),
+            "--dry-run",
+            "--dry-run-dir",
+            plans.to_str().unwrap(),
+        ],
+    )
+    .await;
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    for (n, base_url, user_id) in [
+        (1, "http://pc.invalid", USER_ID),
+        (2, "http://xbox.invalid", xbox_user),
+    ] {
+        let plan = fs::read_to_string(plans.join(format!("contract.HITMAN3.{}.json", n))).unwrap();
+        let plan: Value = serde_json::from_str(&plan).unwrap();
+        for request in plan.as_array().unwrap() {
+            let url = request["url"].as_str().unwrap();
+            assert!(url.starts_with(base_url), "{}", url);
+        }
+        assert_eq!(plan[1]["body"]["userId"], user_id);
+    }
+}
+
+#[tokio::test]
+async fn dry_run_exits_non_zero_on_bad_files() {
+    let dir = temp_dir("dry_run_bad");
+    let contract = write_contract(&dir);
+    let bad = dir.join("bad.json");
+    fs::write(&bad, "{ not json").unwrap();
+    let (contract, bad) = (contract.to_str().unwrap(), bad.to_str().unwrap());
+
+    let output = run(
+        &dir,
+        &["publish", "-f", bad, "--hitman3", "--dry-run", USER_ID],
+    )
+    .await;
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+
+    let output = run(
+        &dir,
+        &["publish", "-f", contract, "-f", bad, "--hitman3", "--dry-run", USER_ID],
+    )
+    .await;
+    assert_eq!(output.status.code(), Some(2));
+    let plan: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(plan.as_array().unwrap().len(), 4);
+}
@@ -0,0 +1,73 @@
+use hitman_contract_creator::mock::{Endpoint, Failure, MockService};
+use hitman_contract_creator::services::{CONTRACTCREATEPAGE_PATH, GETFORPLAY2_PATH};
+use serde_json::{json, Value};
+
+async fn get_for_play2(mock: &MockService) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}{}", mock.base_url(), GETFORPLAY2_PATH))
+        .bearer_auth("token")
+        .body(json!({ "id": "b2aac100-dfc7-4f85-b9cd-528114436f6c" }).to_string())
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn records_requests_and_answers_like_the_service() {
+    let mock = MockService::start().await.unwrap();
+    let response = get_for_play2(&mock).await;
+    assert_eq!(response.status(), 200);
+    let json: Value = response.json().await.unwrap();
+    assert_eq!(
+        json["Contract"]["Metadata"]["Id"],
+        "b2aac100-dfc7-4f85-b9cd-528114436f6c"
+    );
+    assert!(json["ContractSessionId"].is_string());
+
+    let requests = mock.requests_to(Endpoint::GetForPlay2);
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].header("authorization"), Some("Bearer token"));
+    assert_eq!(
+        requests[0].json().unwrap()["id"],
+        "b2aac100-dfc7-4f85-b9cd-528114436f6c"
+    );
+}
+
+#[tokio::test]
+async fn rejects_requests_without_bearer_and_unknown_paths() {
+    let mock = MockService::start().await.unwrap();
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!("{}{}", mock.base_url(), CONTRACTCREATEPAGE_PATH))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+    let response = client
+        .get(format!("{}/unknown", mock.base_url()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+    assert_eq!(mock.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn fails_on_purpose() {
+    let mock = MockService::start().await.unwrap();
+    mock.fail_times(Endpoint::GetForPlay2, Failure::RateLimited, 1);
+    let response = get_for_play2(&mock).await;
+    assert_eq!(response.status(), 429);
+    assert_eq!(response.headers()["retry-after"], "1");
+    assert_eq!(get_for_play2(&mock).await.status(), 200);
+
+    mock.fail(Endpoint::GetForPlay2, Failure::MissingContractSessionId);
+    for _ in 0..2 {
+        let json: Value = get_for_play2(&mock).await.json().await.unwrap();
+        assert!(json.get("ContractSessionId").is_none());
+    }
+    mock.clear_failures();
+    let json: Value = get_for_play2(&mock).await.json().await.unwrap();
+    assert!(json["ContractSessionId"].is_string());
+}
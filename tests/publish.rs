@@ -0,0 +1,667 @@
+use hitman_contract_creator::mock::{Endpoint, Failure, MockService};
+use std::sync::Arc;
+use std::time::Duration;
+
+use hitman_contract_creator::{
+    Account, ClientConfig, Contract, Platform, PublishTypes, RetryPolicy, ServiceError, Services,
+    ServicesConfig,
+};
+use hitman_contract_creator::contract::Event;
+use hitman_contract_creator::event::KillConditions;
+use hitman_contract_creator::services::HeaderProfile;
+use hitman_contract_creator::version::probe_version;
+use serde_json::{json, Value};
+
+const USER_ID: &str = "fe76faee-ecdc-4dd7-a6d5-c5b84054a87c";
+
+fn contract_json() -> Value {
+    json!({
+        "MissionId": "b2aac100-dfc7-4f85-b9cd-528114436f6c",
+        "ExitId": "2f8bd7e4-9154-4c8d-8e1e-9a5b4c5d9a3b",
+        "MissionName": "LOCATION_PARENT_MIAMI",
+        "CreateFromParamsJ": {
+            "creationData": {
+                "Title": "Test contract",
+                "Description": "Test briefing",
+                "Targets": [
+                    { "RepositoryId": "ee454990-0c4b-49e5-9572-a67887325283", "Selected": true },
+                    { "RepositoryId": "c0ab162c-1502-40d4-be0b-2ad1a2e7f6a5", "Selected": true }
+                ],
+                "ContractConditionIds": []
+            }
+        }
+    })
+}
+
+fn fast_retry() -> RetryPolicy {
+    RetryPolicy {
+        initial_backoff: Duration::from_millis(10),
+        ..Default::default()
+    }
+}
+
+async fn publish(mock: &MockService) -> anyhow::Result<hitman_contract_creator::PublishedContract> {
+    let mut contract = Contract::from_contract_json(
+        contract_json(),
+        PublishTypes::HITMAN3,
+        Services::from_base_url(&mock.base_url()),
+    )?
+    .with_retry_policy(fast_retry());
+    contract
+        .publish_contract(&USER_ID.to_string(), &"token".to_string())
+        .await
+}
+
+#[tokio::test]
+async fn publishes_against_mock() {
+    let mock = MockService::start().await.unwrap();
+    let published = publish(&mock).await.unwrap();
+
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 4);
+    assert!(requests[0].path.ends_with("GetForPlay2"));
+    assert!(requests[1].path.ends_with("SaveEvents2"));
+    assert!(requests[2].path.ends_with("contractcreation/create"));
+    assert!(requests[3].path.ends_with("CreateFromParams"));
+
+    let events = requests[1].json().unwrap();
+    assert_eq!(events["userId"], USER_ID);
+    assert_eq!(events["values"].as_array().unwrap().len(), 5);
+
+    let created = requests[3].json().unwrap();
+    assert_eq!(
+        created["creationData"]["ContractId"],
+        published.contract_id.as_str()
+    );
+    assert_eq!(
+        created["creationData"]["ContractPublicId"],
+        published.contract_public_id.as_str()
+    );
+}
+
+#[tokio::test]
+async fn stops_without_contract_session_id() {
+    let mock = MockService::start().await.unwrap();
+    mock.fail(Endpoint::GetForPlay2, Failure::MissingContractSessionId);
+
+    assert!(publish(&mock).await.is_err());
+    assert!(mock.requests_to(Endpoint::SaveEvents2).is_empty());
+}
+
+#[tokio::test]
+async fn reports_failed_create_from_params() {
+    let mock = MockService::start().await.unwrap();
+    mock.fail(Endpoint::CreateFromParams, Failure::ServerError);
+
+    let error = publish(&mock).await.unwrap_err();
+    let error = error.downcast_ref::<ServiceError>().unwrap();
+    assert_eq!(error.endpoint(), Endpoint::CreateFromParams);
+    assert_eq!(error.status().map(|s| s.as_u16()), Some(500));
+}
+
+#[tokio::test]
+async fn retries_idempotent_requests() {
+    let mock = MockService::start().await.unwrap();
+    mock.fail_times(Endpoint::GetForPlay2, Failure::ServerError, 2);
+    mock.fail_times(Endpoint::ContractCreatePage, Failure::RateLimited, 1);
+
+    publish(&mock).await.unwrap();
+    assert_eq!(mock.requests_to(Endpoint::GetForPlay2).len(), 3);
+    assert_eq!(mock.requests_to(Endpoint::ContractCreatePage).len(), 2);
+}
+
+#[tokio::test]
+async fn does_not_retry_save_events_by_default() {
+    let mock = MockService::start().await.unwrap();
+    mock.fail_times(Endpoint::SaveEvents2, Failure::ServerError, 1);
+
+    assert!(publish(&mock).await.is_err());
+    assert_eq!(mock.requests_to(Endpoint::SaveEvents2).len(), 1);
+}
+
+#[tokio::test]
+async fn refreshes_rejected_bearer() {
+    let mock = MockService::start().await.unwrap();
+    mock.fail_times(Endpoint::GetForPlay2, Failure::Unauthorized, 1);
+
+    let mut account = Account {
+        name: Some("main".to_string()),
+        bearer: "stale".to_string(),
+        user_id: Some(USER_ID.to_string()),
+        refresh_token: Some("refresh".to_string()),
+    };
+    let mut contract = Contract::from_contract_json(
+        contract_json(),
+        PublishTypes::HITMAN3,
+        Services::from_base_url(&mock.base_url()),
+    )
+    .unwrap();
+    contract.publish_contract_as(&mut account).await.unwrap();
+
+    assert_ne!(account.bearer, "stale");
+    assert_ne!(account.refresh_token.as_deref(), Some("refresh"));
+    let refresh = mock.requests_to(Endpoint::OAuthToken);
+    assert_eq!(refresh.len(), 1);
+    assert!(refresh[0].body.contains("refresh_token=refresh"));
+    let retried = mock.requests_to(Endpoint::GetForPlay2);
+    assert_eq!(
+        retried[1].header("authorization"),
+        Some(format!("Bearer {}", account.bearer).as_str())
+    );
+}
+
+#[tokio::test]
+async fn refreshes_shared_account_once() {
+    let mock = MockService::start().await.unwrap();
+    let expired = format!(
+        "e30.{}.signature",
+        base64::encode_config(json!({ "exp": 1 }).to_string(), base64::URL_SAFE_NO_PAD)
+    );
+    let account = Arc::new(tokio::sync::Mutex::new(Account {
+        name: Some("main".to_string()),
+        bearer: expired,
+        user_id: Some(USER_ID.to_string()),
+        refresh_token: Some("refresh".to_string()),
+    }));
+
+    // Two jobs of the account at the same time, as with --jobs-per-account 2.
+    let jobs = (0..2)
+        .map(|_| {
+            let account = account.clone();
+            let mut contract = Contract::from_contract_json(
+                contract_json(),
+                PublishTypes::HITMAN3,
+                Services::from_base_url(&mock.base_url()),
+            )
+            .unwrap();
+            tokio::spawn(async move { contract.publish_contract_shared(&account).await })
+        })
+        .collect::<Vec<_>>();
+    for job in jobs {
+        job.await.unwrap().unwrap();
+    }
+
+    let refresh = mock.requests_to(Endpoint::OAuthToken);
+    assert_eq!(refresh.len(), 1);
+    assert!(refresh[0].body.contains("refresh_token=refresh"));
+    let bearer = format!("Bearer {}", account.lock().await.bearer);
+    let opened = mock.requests_to(Endpoint::GetForPlay2);
+    assert_eq!(opened.len(), 2);
+    for request in opened {
+        assert_eq!(request.header("authorization"), Some(bearer.as_str()));
+    }
+}
+
+#[tokio::test]
+async fn sends_refresh_only_once() {
+    let mock = MockService::start().await.unwrap();
+    mock.fail_times(Endpoint::GetForPlay2, Failure::Unauthorized, 1);
+    mock.fail_times(Endpoint::OAuthToken, Failure::ServerError, 1);
+
+    let mut account = Account {
+        name: Some("main".to_string()),
+        bearer: "stale".to_string(),
+        user_id: Some(USER_ID.to_string()),
+        refresh_token: Some("refresh".to_string()),
+    };
+    let mut contract = Contract::from_contract_json(
+        contract_json(),
+        PublishTypes::HITMAN3,
+        Services::from_base_url(&mock.base_url()),
+    )
+    .unwrap()
+    .with_retry_policy(fast_retry());
+    let error = contract.publish_contract_as(&mut account).await.unwrap_err();
+
+    let error = error.downcast_ref::<ServiceError>().unwrap();
+    assert_eq!(error.endpoint(), Endpoint::OAuthToken);
+    assert_eq!(mock.requests_to(Endpoint::OAuthToken).len(), 1);
+    assert_eq!(account.refresh_token.as_deref(), Some("refresh"));
+}
+
+#[tokio::test]
+async fn times_out_on_stalled_service() {
+    // Accepts connections but never answers.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let mut connections = vec![];
+        while let Ok((connection, _)) = listener.accept().await {
+            connections.push(connection);
+        }
+    });
+
+    let client = ClientConfig {
+        timeout: Duration::from_millis(200),
+        ..Default::default()
+    }
+    .build()
+    .unwrap();
+    let mut contract = Contract::from_contract_json(
+        contract_json(),
+        PublishTypes::HITMAN3,
+        Services::from_base_url(&base_url),
+    )
+    .unwrap()
+    .with_client(client)
+    .with_retry_policy(RetryPolicy::none());
+
+    let result = tokio::time::timeout(
+        Duration::from_secs(5),
+        contract.publish_contract(&USER_ID.to_string(), &"token".to_string()),
+    )
+    .await
+    .expect("publish hung on a stalled service");
+    let error = format!("{:#}", result.unwrap_err());
+    assert!(error.contains("GetForPlay2"), "{}", error);
+}
+
+#[tokio::test]
+async fn sends_header_profile_on_every_request() {
+    let mock = MockService::start().await.unwrap();
+    let mut services = Services::from_base_url(&mock.base_url());
+    services.headers.version = Some("8.99.0".to_string());
+    let mut contract =
+        Contract::from_contract_json(contract_json(), PublishTypes::HITMAN3, services).unwrap();
+    contract
+        .publish_contract(&USER_ID.to_string(), &"token".to_string())
+        .await
+        .unwrap();
+
+    let official = HeaderProfile::official(PublishTypes::HITMAN3);
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 4);
+    for request in requests {
+        assert_eq!(request.header("version"), Some("8.99.0"), "{}", request.path);
+        assert_eq!(
+            request.header("user-agent"),
+            official.user_agent.as_deref(),
+            "{}",
+            request.path
+        );
+        assert_eq!(request.header("accept"), official.accept.as_deref());
+        assert_eq!(request.header("content-type"), official.content_type.as_deref());
+    }
+}
+
+#[tokio::test]
+async fn retries_with_fallback_version() {
+    let mock = MockService::start().await.unwrap();
+    mock.accept_versions(&["8.20.0"]);
+    let mut services = Services::from_base_url(&mock.base_url());
+    services.headers.fallback_versions = vec!["8.10.0".to_string(), "8.20.0".to_string()];
+    let mut contract =
+        Contract::from_contract_json(contract_json(), PublishTypes::HITMAN3, services).unwrap();
+    contract
+        .publish_contract(&USER_ID.to_string(), &"token".to_string())
+        .await
+        .unwrap();
+
+    assert_eq!(contract.version(), Some("8.20.0"));
+    let versions = mock
+        .requests_to(Endpoint::GetForPlay2)
+        .iter()
+        .map(|r| r.header("version").unwrap_or("").to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(versions, ["8.7.0", "8.10.0", "8.20.0"]);
+    for request in &mock.requests()[3..] {
+        assert_eq!(request.header("version"), Some("8.20.0"), "{}", request.path);
+    }
+}
+
+#[tokio::test]
+async fn reports_version_mismatch_without_fallbacks() {
+    let mock = MockService::start().await.unwrap();
+    mock.accept_versions(&["8.20.0"]);
+    let error = format!("{:#}", publish(&mock).await.unwrap_err());
+    assert!(error.contains("probe-version"), "{}", error);
+    assert_eq!(mock.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn keeps_version_once_events_are_saved() {
+    let mock = MockService::start().await.unwrap();
+    mock.fail(Endpoint::CreateFromParams, Failure::VersionMismatch);
+    let mut services = Services::from_base_url(&mock.base_url());
+    services.headers.fallback_versions = vec!["8.20.0".to_string()];
+    let mut contract =
+        Contract::from_contract_json(contract_json(), PublishTypes::HITMAN3, services).unwrap();
+    let error = contract
+        .publish_contract(&USER_ID.to_string(), &"token".to_string())
+        .await
+        .unwrap_err();
+
+    assert!(format!("{:#}", error).contains("probe-version"), "{:#}", error);
+    assert_eq!(mock.requests_to(Endpoint::GetForPlay2).len(), 1);
+    assert_eq!(mock.requests_to(Endpoint::SaveEvents2).len(), 1);
+    assert_eq!(mock.requests_to(Endpoint::CreateFromParams).len(), 1);
+}
+
+#[tokio::test]
+async fn probes_accepted_version() {
+    let mock = MockService::start().await.unwrap();
+    mock.accept_versions(&["8.20.0"]);
+    let services =
+        Services::from_base_url(&mock.base_url()).for_game(PublishTypes::HITMAN3);
+    let candidates = ["8.7.0", "8.20.0", "8.30.0"].map(String::from);
+    let version = probe_version(
+        &ClientConfig::default().build().unwrap(),
+        &services,
+        PublishTypes::HITMAN3,
+        "token",
+        &candidates,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(version, "8.20.0");
+    assert_eq!(mock.requests_to(Endpoint::ContractCreatePage).len(), 2);
+}
+
+#[tokio::test]
+async fn publishes_on_locations_of_private_servers() {
+    let mock = MockService::start().await.unwrap();
+    let mut json = contract_json();
+    json["MissionName"] = json!("LOCATION_PARENT_CUSTOM_MAP");
+    let mut contract = Contract::from_contract_json(
+        json.clone(),
+        PublishTypes::HITMAN3,
+        Services::from_base_url(&mock.base_url()),
+    )
+    .unwrap();
+    contract
+        .publish_contract(&USER_ID.to_string(), &"token".to_string())
+        .await
+        .unwrap();
+
+    // Broken contents are still refused before anything is sent.
+    json["CreateFromParamsJ"]["creationData"]["Targets"] = json!([]);
+    let mut contract = Contract::from_contract_json(
+        json,
+        PublishTypes::HITMAN3,
+        Services::from_base_url(&mock.base_url()),
+    )
+    .unwrap();
+    let error = contract
+        .publish_contract(&USER_ID.to_string(), &"token".to_string())
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("Targets"), "{}", error);
+    assert!(!error.to_string().contains("MissionName"), "{}", error);
+    assert_eq!(mock.requests_to(Endpoint::GetForPlay2).len(), 1);
+}
+
+#[tokio::test]
+async fn publishes_hitman1_payloads() {
+    let mock = MockService::start().await.unwrap();
+    let mut json = contract_json();
+    json["MissionName"] = json!("LOCATION_PARENT_COASTALTOWN");
+    let mut services = Services::from_base_url(&mock.base_url());
+    // HITMAN (2016) has no built-in version, so it has to be configured.
+    let without_version =
+        Contract::from_contract_json(json.clone(), PublishTypes::HITMAN1, services.clone());
+    let error = without_version.err().unwrap().to_string();
+    assert!(error.contains("HITMAN1.headers.version"), "{}", error);
+    services.headers.version = Some("1.2.3".to_string());
+    let mut contract =
+        Contract::from_contract_json(json, PublishTypes::HITMAN1, services).unwrap();
+    contract
+        .publish_contract(&USER_ID.to_string(), &"token".to_string())
+        .await
+        .unwrap();
+
+    for request in mock.requests() {
+        assert_eq!(request.header("version"), Some("1.2.3"), "{}", request.path);
+    }
+    let events = mock.requests_to(Endpoint::SaveEvents2)[0].json().unwrap();
+    let start = &events["values"][0];
+    assert_eq!(start["Name"], "ContractStart");
+    assert_eq!(start["Value"]["LocationId"], "LOCATION_PARENT_COASTALTOWN");
+    assert!(start["Value"].get("SelectedCharacterId").is_none());
+    let create = mock.requests_to(Endpoint::CreateFromParams)[0].json().unwrap();
+    assert!(create["creationData"].get("ContractConditionIds").is_none());
+}
+
+#[tokio::test]
+async fn picks_services_of_platform() {
+    let mock = MockService::start().await.unwrap();
+    let config: ServicesConfig = serde_json::from_value(json!({
+        "HITMAN3": { "headers": { "version": "8.99.0" } },
+        "platforms": {
+            "epic": { "HITMAN3": { "base_url": mock.base_url() } }
+        }
+    }))
+    .unwrap();
+
+    let epic = config
+        .services_on(PublishTypes::HITMAN3, Platform::from_claim("epic"))
+        .unwrap();
+    assert_eq!(epic.base_url, mock.base_url());
+    assert_eq!(epic.headers.version.as_deref(), Some("8.99.0"));
+    let steam = config
+        .services_on(PublishTypes::HITMAN3, Platform::from_claim("steam"))
+        .unwrap();
+    assert_eq!(steam.base_url, "https://hm3-service.hitman.io");
+    let error = config
+        .services_on(PublishTypes::HITMAN3, Some(Platform::Xbox))
+        .unwrap_err();
+    assert!(format!("{:#}", error).contains("platforms.xbox.HITMAN3"));
+
+    let mut contract =
+        Contract::from_contract_json(contract_json(), PublishTypes::HITMAN3, epic).unwrap();
+    contract
+        .publish_contract(&USER_ID.to_string(), &"token".to_string())
+        .await
+        .unwrap();
+    assert_eq!(mock.requests().len(), 4);
+}
+
+#[tokio::test]
+async fn sends_custom_playthrough() {
+    let mock = MockService::start().await.unwrap();
+    let target = "ee454990-0c4b-49e5-9572-a67887325283".to_string();
+    let guard = "5b54d9fb-fa85-4302-a8d5-c5c5e97344c8".to_string();
+    let outfit = "fae73e92-2307-4163-bb93-0a5b2a3cbb23".to_string();
+    let mut contract = Contract::from_contract_json(
+        contract_json(),
+        PublishTypes::HITMAN3,
+        Services::from_base_url(&mock.base_url()),
+    )
+    .unwrap()
+    .with_events(vec![
+        Event::ContractStartEvent,
+        Event::IntroCutEndEvent,
+        Event::PacifyEvent { repository_id: guard.clone() },
+        Event::DisguiseEvent { repository_id: outfit.clone() },
+        Event::ItemPickedUpEvent { repository_id: outfit.clone() },
+        Event::SpottedEvent { repository_ids: vec![guard.clone()] },
+        Event::SetpiecesEvent {
+            repository_id: target.clone(),
+            name: "Chandelier".to_string(),
+            setpiece_type: "Accident".to_string(),
+        },
+        Event::KillEvent {
+            repository_id: target.clone(),
+            conditions: KillConditions::default(),
+        },
+        Event::AccidentBodyFoundEvent { repository_id: target.clone() },
+        Event::BodyFoundEvent { repository_id: guard.clone() },
+        Event::AllBodiesHiddenEvent,
+        Event::ExitGateEvent,
+        Event::ContractEndEvent,
+    ]);
+    contract
+        .publish_contract(&USER_ID.to_string(), &"token".to_string())
+        .await
+        .unwrap();
+
+    let events = mock.requests_to(Endpoint::SaveEvents2)[0].json().unwrap();
+    let events = events["values"].as_array().unwrap();
+    let names = events.iter().map(|e| e["Name"].as_str().unwrap()).collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            "ContractStart",
+            "IntroCutEnd",
+            "Pacify",
+            "Disguise",
+            "ItemPickedUp",
+            "Spotted",
+            "setpieces",
+            "Kill",
+            "AccidentBodyFound",
+            "BodyFound",
+            "AllBodiesHidden",
+            "exit_gate",
+            "ContractEnd",
+        ]
+    );
+    for event in events {
+        assert_eq!(event["UserId"], USER_ID);
+        assert_eq!(event["ContractId"], contract_json()["MissionId"]);
+    }
+    assert_eq!(events[2]["Value"]["RepositoryId"], guard);
+    assert_eq!(events[2]["Value"]["KillMethodBroad"], "");
+    // The guard isn't a target of the contract and is only knocked out.
+    assert_eq!(events[2]["Value"]["IsTarget"], false);
+    assert_eq!(events[2]["Value"]["TotalDamage"], 0.0);
+    assert_eq!(events[3]["Value"], outfit);
+    assert_eq!(events[4]["Value"]["RepositoryId"], outfit);
+    assert_eq!(events[5]["Value"], json!([guard]));
+    assert_eq!(events[6]["Value"]["name_pq"], "Chandelier");
+    assert_eq!(events[8]["Value"]["DeadBody"]["RepositoryId"], target);
+    assert_eq!(events[9]["Value"]["DeadBody"]["RepositoryId"], guard);
+    assert_eq!(events[10]["Value"], "");
+}
+
+#[tokio::test]
+async fn kills_the_way_targets_require() {
+    let mock = MockService::start().await.unwrap();
+    let pistol = "1e11fb09-b5a5-4c4e-9c31-d7b8f5e5e5c4";
+    let outfit = "fae73e92-2307-4163-bb93-0a5b2a3cbb23";
+    let mut json = contract_json();
+    json["CreateFromParamsJ"]["creationData"]["Targets"] = json!([
+        {
+            "RepositoryId": "ee454990-0c4b-49e5-9572-a67887325283",
+            "Selected": true,
+            "Weapon": {
+                "RepositoryId": pistol,
+                "KillMethodBroad": "pistol",
+                "KillMethodStrict": "close_combat_pistol_elimination",
+            },
+            "Outfit": { "RepositoryId": outfit, "Required": true, "IsHitmanSuit": false },
+        },
+        {
+            "RepositoryId": "c0ab162c-1502-40d4-be0b-2ad1a2e7f6a5",
+            "Selected": true,
+            "Weapon": { "KillMethodBroad": "accident", "KillMethodStrict": "accident_drown" },
+            "Outfit": { "RepositoryId": outfit, "Required": false },
+        },
+    ]);
+    let mut contract = Contract::from_contract_json(
+        json,
+        PublishTypes::HITMAN3,
+        Services::from_base_url(&mock.base_url()),
+    )
+    .unwrap();
+    contract
+        .publish_contract(&USER_ID.to_string(), &"token".to_string())
+        .await
+        .unwrap();
+
+    let events = mock.requests_to(Endpoint::SaveEvents2)[0].json().unwrap();
+    let events = events["values"].as_array().unwrap();
+    let names = events
+        .iter()
+        .map(|e| e["Name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        ["ContractStart", "IntroCutEnd", "Disguise", "Kill", "Kill", "exit_gate"]
+    );
+    assert_eq!(events[2]["Value"], outfit);
+
+    let shot = &events[3]["Value"];
+    assert_eq!(shot["KillItemRepositoryId"], pistol);
+    assert_eq!(shot["KillItemCategory"], "pistol");
+    assert_eq!(shot["KillMethodBroad"], "pistol");
+    assert_eq!(shot["KillMethodStrict"], "close_combat_pistol_elimination");
+    assert_eq!(shot["IsHeadshot"], false);
+    assert_eq!(shot["WeaponSilenced"], false);
+    assert_eq!(shot["Projectile"], true);
+    assert_eq!(shot["Accident"], false);
+    assert_eq!(shot["Sniper"], false);
+    assert_eq!(shot["OutfitRepositoryId"], outfit);
+    assert_eq!(shot["OutfitIsHitmanSuit"], false);
+
+    let drowning = &events[4]["Value"];
+    assert_eq!(drowning["KillItemRepositoryId"], "");
+    assert_eq!(drowning["KillItemCategory"], "");
+    assert_eq!(drowning["KillMethodStrict"], "accident_drown");
+    assert_eq!(drowning["Accident"], true);
+    assert_eq!(drowning["IsHeadshot"], false);
+    assert_eq!(drowning["Projectile"], false);
+    // Still in the disguise put on for the first kill.
+    assert_eq!(drowning["OutfitRepositoryId"], outfit);
+    assert_eq!(drowning["OutfitIsHitmanSuit"], false);
+}
+
+#[tokio::test]
+async fn keeps_disguise_on_for_later_kills() {
+    let mock = MockService::start().await.unwrap();
+    let chef = "fae73e92-2307-4163-bb93-0a5b2a3cbb23";
+    let guard = "2ac0e8b9-5d1e-4b1f-8f0e-4c7b7e1c9d2a";
+    let mut json = contract_json();
+    json["CreateFromParamsJ"]["creationData"]["Targets"] = json!([
+        {
+            "RepositoryId": "ee454990-0c4b-49e5-9572-a67887325283",
+            "Outfit": { "RepositoryId": chef, "Required": true },
+        },
+        { "RepositoryId": "c0ab162c-1502-40d4-be0b-2ad1a2e7f6a5" },
+        {
+            "RepositoryId": "5b54d9fb-fa85-4302-a8d5-c5c5e97344c8",
+            "Outfit": { "RepositoryId": chef, "Required": true },
+        },
+        {
+            "RepositoryId": "b87b242e-4ef6-4ea1-9cbd-8a2c0a7b8a6e",
+            "Outfit": { "RepositoryId": guard, "Required": true },
+        },
+    ]);
+    let mut contract = Contract::from_contract_json(
+        json,
+        PublishTypes::HITMAN3,
+        Services::from_base_url(&mock.base_url()),
+    )
+    .unwrap();
+    contract
+        .publish_contract(&USER_ID.to_string(), &"token".to_string())
+        .await
+        .unwrap();
+
+    let events = mock.requests_to(Endpoint::SaveEvents2)[0].json().unwrap();
+    let events = events["values"].as_array().unwrap();
+    let names = events
+        .iter()
+        .map(|e| e["Name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            "ContractStart",
+            "IntroCutEnd",
+            "Disguise",
+            "Kill",
+            "Kill",
+            "Kill",
+            "Disguise",
+            "Kill",
+            "exit_gate"
+        ]
+    );
+    assert_eq!(events[2]["Value"], chef);
+    assert_eq!(events[6]["Value"], guard);
+    for (kill, outfit) in [(3, chef), (4, chef), (5, chef), (7, guard)] {
+        assert_eq!(events[kill]["Value"]["OutfitRepositoryId"], outfit, "{}", kill);
+        assert_eq!(events[kill]["Value"]["OutfitIsHitmanSuit"], false, "{}", kill);
+    }
+}
@@ -0,0 +1,140 @@
+//! Snapshots of the SaveEvents2 body of every event kind, per title.
+//!
+//! Run with `UPDATE_SNAPSHOTS=1` to rewrite `tests/snapshots/` after an intended change.
+
+use std::fs;
+use std::path::Path;
+
+use hitman_contract_creator::contract::Event;
+use hitman_contract_creator::event::KillConditions;
+use hitman_contract_creator::{Contract, PublishTypes, Services};
+use serde_json::{json, Value};
+
+const USER_ID: &str = "fe76faee-ecdc-4dd7-a6d5-c5b84054a87c";
+
+fn contract_json() -> Value {
+    json!({
+        "MissionId": "b2aac100-dfc7-4f85-b9cd-528114436f6c",
+        "ExitId": "2f8bd7e4-9154-4c8d-8e1e-9a5b4c5d9a3b",
+        "MissionName": "LOCATION_PARENT_PARIS",
+        "CreateFromParamsJ": {
+            "creationData": {
+                "Title": "Test contract",
+                "Description": "Test briefing",
+                "Targets": [
+                    { "RepositoryId": "ee454990-0c4b-49e5-9572-a67887325283", "Selected": true }
+                ],
+                "ContractConditionIds": []
+            }
+        }
+    })
+}
+
+fn every_event() -> Vec<Event> {
+    let target = "ee454990-0c4b-49e5-9572-a67887325283".to_string();
+    let guard = "c0ab162c-1502-40d4-be0b-2ad1a2e7f6a5".to_string();
+    let outfit = "fae73e92-2307-4163-bb93-0a5b2a3cbb23".to_string();
+    vec![
+        Event::ContractStartEvent,
+        Event::IntroCutEndEvent,
+        Event::PacifyEvent {
+            repository_id: guard.clone(),
+        },
+        Event::DisguiseEvent {
+            repository_id: outfit.clone(),
+        },
+        Event::ItemPickedUpEvent {
+            repository_id: outfit.clone(),
+        },
+        Event::SpottedEvent {
+            repository_ids: vec![guard.clone()],
+        },
+        Event::SetpiecesEvent {
+            repository_id: target.clone(),
+            name: "Chandelier".to_string(),
+            setpiece_type: "Accident".to_string(),
+        },
+        Event::KillEvent {
+            repository_id: target.clone(),
+            conditions: KillConditions::default(),
+        },
+        Event::KillEvent {
+            repository_id: target.clone(),
+            conditions: KillConditions {
+                kill_method_broad: Some("sniperrifle".to_string()),
+                kill_method_strict: Some("sniperrifle".to_string()),
+                weapon_repository_id: Some("370580fc-7fcf-47f8-b994-cebd279f69f9".to_string()),
+                outfit_repository_id: Some(outfit.clone()),
+            },
+        },
+        Event::AccidentBodyFoundEvent {
+            repository_id: target,
+        },
+        Event::BodyFoundEvent {
+            repository_id: guard,
+        },
+        Event::AllBodiesHiddenEvent,
+        Event::ExitGateEvent,
+        Event::ContractEndEvent,
+    ]
+}
+
+/// The SaveEvents2 body for `game`, with the IDs that are new on every run masked.
+fn save_events_body(game: PublishTypes) -> Value {
+    let mut services = Services::from_base_url("http://127.0.0.1");
+    // HITMAN (2016) has no built-in version, and the body doesn't depend on it.
+    services.headers.version = Some("1.2.3".to_string());
+    let mut contract = Contract::from_contract_json(contract_json(), game, services)
+        .unwrap()
+    .with_events(every_event());
+    let mut body = contract
+        .dry_run(&USER_ID.to_string())
+        .unwrap()
+        .into_iter()
+        .find(|request| request.url.ends_with("SaveEvents2"))
+        .and_then(|request| request.body)
+        .unwrap();
+    for event in body["values"].as_array_mut().unwrap() {
+        event["Id"] = json!("<Id>");
+        event["SessionId"] = json!("<SessionId>");
+        if event["Name"] == "ItemPickedUp" {
+            event["Value"]["InstanceId"] = json!("<InstanceId>");
+        }
+    }
+    body
+}
+
+fn assert_snapshot(game: PublishTypes) {
+    let actual = serde_json::to_string_pretty(&save_events_body(game)).unwrap() + "\n";
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(format!("save_events.{:?}.json", game));
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, &actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("{}: {}, run with UPDATE_SNAPSHOTS=1", path.display(), e));
+    assert!(
+        actual == expected,
+        "{} is out of date, run with UPDATE_SNAPSHOTS=1 if the change is intended:\n{}",
+        path.display(),
+        actual
+    );
+}
+
+#[test]
+fn save_events_hitman2() {
+    assert_snapshot(PublishTypes::HITMAN2);
+}
+
+#[test]
+fn save_events_hitman3() {
+    assert_snapshot(PublishTypes::HITMAN3);
+}
+
+#[test]
+fn save_events_hitman1() {
+    assert_snapshot(PublishTypes::HITMAN1);
+}